@@ -0,0 +1,215 @@
+use {
+    crate::params::{Field, Params},
+    quote::quote,
+    syn::{parse_quote, DeriveInput, WherePredicate},
+};
+
+/// Implements `naphtha::audit::Audited` for models marked by `audited`.
+/// The values of all persisted columns except the primary key are recorded.
+pub(crate) fn impl_audited(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+) -> ::proc_macro2::TokenStream {
+    if !params.audited {
+        return quote! {};
+    }
+    let name = &ast.ident;
+
+    let audited = fields
+        .iter()
+        .filter(|f| !f.skip && !params.is_primary_key(&f.ident))
+        .collect::<Vec<_>>();
+    let values = audited.iter().map(|f| {
+        let ident = &f.ident;
+        let column = f.column.to_string();
+        quote! { (#column, ::naphtha::serde_json::to_value(&self.#ident)?) }
+    });
+    let sensitive = audited
+        .iter()
+        .filter(|f| f.sensitive)
+        .map(|f| f.column.to_string());
+
+    let bounds = audited
+        .iter()
+        .filter(|f| crate::generics::uses_type_param(&ast.generics, &f.ty))
+        .map(|f| {
+            let ty = &f.ty;
+            parse_quote!(#ty: ::naphtha::serde::Serialize)
+        })
+        .collect();
+    let generics = crate::generics::extend(&ast.generics, vec![], bounds);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::naphtha::audit::Audited for #name #ty_generics
+        #where_clause
+        {
+            const SENSITIVE_COLUMNS: &'static [&'static str] = &[#(#sensitive),*];
+
+            fn audit_values(
+                &self,
+            ) -> ::naphtha::serde_json::Result<Vec<(&'static str, ::naphtha::serde_json::Value)>> {
+                Ok(vec![#(#values),*])
+            }
+        }
+    }
+}
+
+/// Returns the bounds required by the modifier of an audited model.
+pub(crate) fn bounds(params: &Params) -> Vec<WherePredicate> {
+    if !params.audited {
+        return vec![];
+    }
+    vec![
+        parse_quote!(Self: ::naphtha::audit::Audited),
+        parse_quote! {
+            <Self as ::naphtha::DatabaseModel>::PrimaryKey: ::naphtha::serde::Serialize
+        },
+    ]
+}
+
+/// Creates the audit log table on the locked connection `c` within the up
+/// migration of an audited model, unless it exists.
+#[cfg(any(
+    feature = "barrel-sqlite",
+    feature = "barrel-mysql",
+    feature = "barrel-pg"
+))]
+pub(crate) fn create_table_migration(
+    params: &Params,
+) -> ::proc_macro2::TokenStream {
+    if !params.audited {
+        return quote! {};
+    }
+    let record_error = crate::instrument::record_error(quote! { msg });
+    quote! {
+        if let Err(msg) = ::naphtha::audit::AuditBackend::create_audit_table(&*c) {
+            #record_error
+            error!("Could not create the audit table in DatabaseSqlMigrationExecutor::execute_migration_up: {}", msg);
+            return Err(msg.to_string());
+        }
+    }
+}
+
+/// Reads the actor of the connection `conn` before the changes are written.
+pub(crate) fn read_actor(params: &Params) -> ::proc_macro2::TokenStream {
+    if !params.audited {
+        return quote! {};
+    }
+    quote! { let __naphtha_actor = conn.actor(); }
+}
+
+/// Records the insertion after the given query, that returns the primary key
+/// of the inserted model. It is executed within the insert transaction.
+pub(crate) fn audit_insert(
+    params: &Params,
+    insert_query: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if !params.audited {
+        return insert_query;
+    }
+    let record = record(
+        quote! { "insert" },
+        quote! { &key },
+        quote! { None },
+        quote! { Some(&*self) },
+    );
+    quote! {
+        let key: ::naphtha::diesel::result::QueryResult<<Self as ::naphtha::DatabaseModel>::PrimaryKey> = {
+            #insert_query
+        };
+        let key = key?;
+        #record?;
+        Ok(key)
+    }
+}
+
+/// Executes the given update statement within a transaction and records the
+/// changes compared to the row before.
+pub(crate) fn audit_update(
+    params: &Params,
+    update_statement: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if !params.audited {
+        return update_statement;
+    }
+    let load = load_row(params);
+    let record = record(
+        quote! { "update" },
+        quote! { &::naphtha::DatabaseModel::primary_key(&*self) },
+        quote! { Some(&before) },
+        quote! { Some(&*self) },
+    );
+    quote! {
+        ::naphtha::diesel::Connection::transaction::<_, ::naphtha::diesel::result::Error, _>(&*c, || {
+            let before = #load?;
+            let result = #update_statement?;
+            #record?;
+            Ok(result)
+        })
+    }
+}
+
+/// Executes the given delete statement within a transaction and records the
+/// removed row, if there was one.
+pub(crate) fn audit_remove(
+    params: &Params,
+    delete: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if !params.audited {
+        return delete;
+    }
+    let load = load_row(params);
+    let record = record(
+        quote! { "remove" },
+        quote! { &::naphtha::DatabaseModel::primary_key(&*self) },
+        quote! { Some(&before) },
+        quote! { None },
+    );
+    quote! {
+        ::naphtha::diesel::Connection::transaction::<_, ::naphtha::diesel::result::Error, _>(&*c, || {
+            let before = ::naphtha::diesel::OptionalExtension::optional(#load)?;
+            let rows = { #delete }?;
+            if let Some(before) = before {
+                #record?;
+            }
+            Ok(rows)
+        })
+    }
+}
+
+/// Returns the query loading the current row of the model `self` from the
+/// connection `c`.
+fn load_row(params: &Params) -> ::proc_macro2::TokenStream {
+    let table = params.table_path();
+    quote! {
+        ::naphtha::diesel::RunQueryDsl::get_result::<Self>(
+            ::naphtha::diesel::QueryDsl::find(
+                #table::table,
+                ::naphtha::DatabaseModel::primary_key(&*self),
+            ),
+            &*c,
+        )
+    }
+}
+
+/// Returns the call writing the audit log entry to the connection `c`.
+fn record(
+    operation: ::proc_macro2::TokenStream,
+    key: ::proc_macro2::TokenStream,
+    before: ::proc_macro2::TokenStream,
+    after: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    quote! {
+        ::naphtha::audit::record::<Self, _>(
+            &*c,
+            #operation,
+            #key,
+            #before,
+            #after,
+            __naphtha_actor.as_deref(),
+        )
+    }
+}
@@ -0,0 +1,173 @@
+use {
+    quote::quote,
+    syn::{parse_quote, DeriveInput},
+};
+
+pub(crate) fn impl_any(
+    ast: &DeriveInput,
+    params: &crate::params::Params,
+    fields: &[crate::params::Field],
+) -> ::proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let enter_up =
+        crate::instrument::enter_operation("migration_up", ast, params);
+    let enter_down =
+        crate::instrument::enter_operation("migration_down", ast, params);
+    let record_error = crate::instrument::record_error(quote! { msg });
+    let create_audit_table = crate::audit::create_table_migration(params);
+
+    let generics = crate::generics::extend(
+        &ast.generics,
+        vec![],
+        vec![parse_quote!(Self: ::naphtha::barrel::DatabaseSqlMigration)],
+    );
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    let execute_up = execute(params, fields, true);
+    let execute_down = execute(params, fields, false);
+
+    quote! {
+        impl #impl_generics ::naphtha::barrel::DatabaseSqlMigrationExecutor<::naphtha::AnyConnection, usize> for #name #ty_generics
+        #where_clause
+        {
+            fn execute_migration_up(conn: &::naphtha::DatabaseConnection<::naphtha::AnyConnection>) -> Result<usize, String> {
+                use {
+                    ::naphtha::{barrel::Migration, DatabaseConnection, log::error, diesel::RunQueryDsl},
+                };
+                #enter_up
+                let mut m = Migration::new();
+                <Self as ::naphtha::barrel::DatabaseSqlMigration>::migration_up(&mut m);
+
+                let c = match conn.lock() {
+                    Ok(c) => c,
+                    Err(msg) => {
+                        #record_error
+                        error!("Could not aquire lock on DatabaseSqlMigrationExecutor::execute_migration_up: {}", msg.to_string());
+                        return Err(msg.to_string());
+                    }
+                };
+
+                #create_audit_table
+                #execute_up
+            }
+
+            fn execute_migration_down(conn: &::naphtha::DatabaseConnection<::naphtha::AnyConnection>) -> Result<usize, String> {
+                use {
+                    ::naphtha::{barrel::Migration, DatabaseConnection, diesel::RunQueryDsl, log::error},
+                };
+                #enter_down
+                let mut m = Migration::new();
+                <Self as ::naphtha::barrel::DatabaseSqlMigration>::migration_down(&mut m);
+
+                let c = match conn.lock() {
+                    Ok(c) => c,
+                    Err(msg) => {
+                        #record_error
+                        error!("Could not aquire lock on DatabaseSqlMigrationExecutor::execute_migration_down for model: {}", msg.to_string());
+                        return Err(msg.to_string());
+                    }
+                };
+
+                #execute_down
+            }
+        }
+    }
+}
+
+/// Returns a `match` on the locked `AnyConnection` that creates the SQL of
+/// the migration `m` for the connected backend and executes it, followed by
+/// the `up` or down migration of the history table of `temporal` models.
+/// Backends without enabled barrel feature return an error.
+fn execute(
+    params: &crate::params::Params,
+    fields: &[crate::params::Field],
+    up: bool,
+) -> ::proc_macro2::TokenStream {
+    // unused without any enabled barrel feature
+    #[allow(unused_variables)]
+    let history = |backend: ::proc_macro2::TokenStream, column_type: &str| {
+        if up {
+            crate::temporal::history_migration_up(
+                params,
+                fields,
+                backend,
+                column_type,
+            )
+        } else {
+            crate::temporal::history_migration_down(params, backend)
+        }
+    };
+    let mut arms = vec![];
+    #[cfg(all(feature = "sqlite", feature = "barrel-sqlite"))]
+    {
+        let history =
+            history(quote! { ::naphtha::barrel::backend::Sqlite }, "SQLITE");
+        let execute = crate::temporal::execute_history_migration(
+            params,
+            quote! { ::naphtha::diesel::sql_query(m).execute(c) },
+        );
+        arms.push(quote! {
+            ::naphtha::AnyConnection::Sqlite(c) => {
+                let m = m.make::<::naphtha::barrel::backend::Sqlite>();
+                #history
+                #execute
+            }
+        });
+    }
+    #[cfg(all(feature = "sqlite", not(feature = "barrel-sqlite")))]
+    arms.push(quote! {
+        ::naphtha::AnyConnection::Sqlite(_) => {
+            return Err("Migrations on SQLite require the barrel-sqlite feature!".to_string());
+        }
+    });
+    #[cfg(all(feature = "mysql", feature = "barrel-mysql"))]
+    {
+        let history =
+            history(quote! { ::naphtha::barrel::backend::MySql }, "MYSQL");
+        let execute = crate::temporal::execute_history_migration(
+            params,
+            quote! { ::naphtha::diesel::sql_query(m).execute(c) },
+        );
+        arms.push(quote! {
+            ::naphtha::AnyConnection::Mysql(c) => {
+                let m = m.make::<::naphtha::barrel::backend::MySql>();
+                #history
+                #execute
+            }
+        });
+    }
+    #[cfg(all(feature = "mysql", not(feature = "barrel-mysql")))]
+    arms.push(quote! {
+        ::naphtha::AnyConnection::Mysql(_) => {
+            return Err("Migrations on MySQL require the barrel-mysql feature!".to_string());
+        }
+    });
+    #[cfg(all(feature = "pg", feature = "barrel-pg"))]
+    {
+        let history = history(quote! { ::naphtha::barrel::backend::Pg }, "PG");
+        let execute = crate::temporal::execute_history_migration(
+            params,
+            quote! { ::naphtha::diesel::sql_query(m).execute(c) },
+        );
+        arms.push(quote! {
+            ::naphtha::AnyConnection::Pg(c) => {
+                let m = m.make::<::naphtha::barrel::backend::Pg>();
+                #history
+                #execute
+            }
+        });
+    }
+    #[cfg(all(feature = "pg", not(feature = "barrel-pg")))]
+    arms.push(quote! {
+        ::naphtha::AnyConnection::Pg(_) => {
+            return Err("Migrations on PostgreSQL require the barrel-pg feature!".to_string());
+        }
+    });
+
+    super::migration_result(quote! {
+        match &*c {
+            #(#arms)*
+        }
+    })
+}
@@ -1,6 +1,32 @@
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+pub(crate) mod any;
 #[cfg(feature = "barrel-mysql")]
 pub(crate) mod mysql;
 #[cfg(feature = "barrel-pg")]
 pub(crate) mod pg;
 #[cfg(feature = "barrel-sqlite")]
 pub(crate) mod sqlite;
+
+/// Returns the result of the executed migration `query`, converting the error
+/// into a `String`.
+pub(crate) fn migration_result(
+    query: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    let record_rows =
+        crate::instrument::record_rows_affected(quote::quote! { u });
+    let record_error = crate::instrument::record_error(quote::quote! { msg });
+    let record_success = crate::instrument::record_success();
+    quote::quote! {
+        match #query {
+            Ok(u) => {
+                #record_rows
+                #record_success
+                Ok(u)
+            }
+            Err(msg) => {
+                #record_error
+                Err(msg.to_string())
+            }
+        }
+    }
+}
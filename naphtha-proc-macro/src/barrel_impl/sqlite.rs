@@ -1,55 +1,90 @@
-use {quote::quote, syn::DeriveInput};
+use {
+    quote::quote,
+    syn::{parse_quote, DeriveInput},
+};
 
-pub(crate) fn impl_sqlite(ast: &DeriveInput) -> ::proc_macro2::TokenStream {
+pub(crate) fn impl_sqlite(
+    ast: &DeriveInput,
+    params: &crate::params::Params,
+    fields: &[crate::params::Field],
+) -> ::proc_macro2::TokenStream {
     let name = &ast.ident;
+    let enter_up =
+        crate::instrument::enter_operation("migration_up", ast, params);
+    let enter_down =
+        crate::instrument::enter_operation("migration_down", ast, params);
+    let record_error = crate::instrument::record_error(quote! { msg });
+    let create_audit_table = crate::audit::create_table_migration(params);
+
+    let generics = crate::generics::extend(
+        &ast.generics,
+        vec![],
+        vec![parse_quote!(Self: ::naphtha::barrel::DatabaseSqlMigration)],
+    );
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    let result =
+        super::migration_result(crate::temporal::execute_history_migration(
+            params,
+            quote! { ::naphtha::diesel::sql_query(m).execute(&*c) },
+        ));
+    let backend = quote! { ::naphtha::barrel::backend::Sqlite };
+    let history_up = crate::temporal::history_migration_up(
+        params,
+        fields,
+        backend.clone(),
+        "SQLITE",
+    );
+    let history_down = crate::temporal::history_migration_down(params, backend);
 
     quote! {
-        impl ::naphtha::barrel::DatabaseSqlMigrationExecutor<::naphtha::diesel::SqliteConnection, usize> for #name
-        where
-            Self: ::naphtha::barrel::DatabaseSqlMigration,
+        impl #impl_generics ::naphtha::barrel::DatabaseSqlMigrationExecutor<::naphtha::diesel::SqliteConnection, usize> for #name #ty_generics
+        #where_clause
         {
             fn execute_migration_up(conn: &::naphtha::DatabaseConnection<::naphtha::diesel::SqliteConnection>) -> Result<usize, String> {
                 use {
                     ::naphtha::{barrel::Migration, DatabaseConnection, log::error, diesel::RunQueryDsl},
                 };
+                #enter_up
                 let mut m = Migration::new();
-                Self::migration_up(&mut m);
+                <Self as ::naphtha::barrel::DatabaseSqlMigration>::migration_up(&mut m);
                 let m = m.make::<::naphtha::barrel::backend::Sqlite>();
+                #history_up
 
                 let c = match conn.lock() {
                     Ok(c) => c,
                     Err(msg) => {
+                        #record_error
                         error!("Could not aquire lock on DatabaseSqlMigrationExecutor::execute_migration_up: {}", msg.to_string());
                         return Err(msg.to_string());
                     }
                 };
 
-                match ::naphtha::diesel::sql_query(m).execute(&*c) {
-                    Ok(u) => Ok(u),
-                    Err(msg) => Err(msg.to_string()),
-                }
+                #create_audit_table
+                #result
             }
 
             fn execute_migration_down(conn: &::naphtha::DatabaseConnection<::naphtha::diesel::SqliteConnection>) -> Result<usize, String> {
                 use {
                     ::naphtha::{barrel::Migration, DatabaseConnection, diesel::RunQueryDsl, log::error},
                 };
+                #enter_down
                 let mut m = Migration::new();
-                Self::migration_down(&mut m);
+                <Self as ::naphtha::barrel::DatabaseSqlMigration>::migration_down(&mut m);
                 let m = m.make::<::naphtha::barrel::backend::Sqlite>();
+                #history_down
 
                 let c = match conn.lock() {
                     Ok(c) => c,
                     Err(msg) => {
+                        #record_error
                         error!("Could not aquire lock on DatabaseSqlMigrationExecutor::execute_migration_down for model: {}", msg.to_string());
                         return Err(msg.to_string());
                     }
                 };
 
-                match ::naphtha::diesel::sql_query(m).execute(&*c) {
-                    Ok(u) => Ok(u),
-                    Err(msg) => Err(msg.to_string()),
-                }
+                #result
             }
         }
     }
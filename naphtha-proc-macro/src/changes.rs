@@ -0,0 +1,67 @@
+use {
+    crate::params::{Field, Params},
+    quote::quote,
+    syn::DeriveInput,
+};
+
+/// Implements `naphtha::changes::QueryByRowid`, loading the model from the
+/// row with the given `rowid` on the primary SQLite connection.
+pub(crate) fn impl_query_by_rowid(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+) -> ::proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let backend = quote! { ::naphtha::diesel::sqlite::Sqlite };
+    let bounds =
+        crate::generics::field_bounds(&ast.generics, params, fields, &backend);
+    let generics = crate::generics::extend(&ast.generics, vec![], bounds);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    let sql_types = fields.iter().filter(|f| !f.skip).map(|f| {
+        let column = params.column_path(f);
+        quote! { <#column as ::naphtha::diesel::Expression>::SqlType }
+    });
+    let columns = crate::temporal::column_names(fields)
+        .iter()
+        .map(|column| quote_identifier(column))
+        .collect::<Vec<_>>();
+    let select = format!(
+        "SELECT {} FROM {} WHERE rowid = ",
+        columns.join(", "),
+        quote_identifier(&params.table_name)
+    );
+    let statement = crate::instrument::run_statement(
+        ast,
+        quote! {
+            ::naphtha::diesel::dsl::sql::<(#(#sql_types,)*)>(#select)
+                .bind::<::naphtha::diesel::sql_types::BigInt, _>(rowid)
+        },
+        quote! { query.get_result::<Self>(&*c) },
+    );
+    let enter_operation =
+        crate::instrument::enter_operation("query_by_rowid", ast, params);
+    let record_rowid = crate::instrument::record_primary_key(quote! { rowid });
+    let record_result = crate::instrument::map_record_result();
+
+    quote! {
+        impl #impl_generics ::naphtha::changes::QueryByRowid for #name #ty_generics
+        #where_clause
+        {
+            #[track_caller]
+            fn query_by_rowid(conn: &::naphtha::DatabaseConnection<::naphtha::diesel::SqliteConnection>, rowid: i64)
+                -> Result<Self, ::naphtha::ConnectionError> {
+                use ::naphtha::diesel::RunQueryDsl;
+                #enter_operation
+                #record_rowid
+                conn.primary().read::<Self, _>(|c| #statement)#record_result
+            }
+        }
+    }
+}
+
+/// Quotes the given SQL identifier for SQLite.
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
@@ -0,0 +1,43 @@
+use {super::Connection, quote::quote};
+
+/// Returns the connection to any of the enabled backends.
+pub(crate) fn connection() -> Connection {
+    Connection {
+        ty: quote! { ::naphtha::AnyConnection },
+        backends: backends(),
+        dispatch,
+    }
+}
+
+/// Returns the database backends that are enabled.
+fn backends() -> Vec<::proc_macro2::TokenStream> {
+    let mut backends = vec![];
+    #[cfg(feature = "sqlite")]
+    backends.push(quote! { ::naphtha::diesel::sqlite::Sqlite });
+    #[cfg(feature = "mysql")]
+    backends.push(quote! { ::naphtha::diesel::mysql::Mysql });
+    #[cfg(feature = "pg")]
+    backends.push(quote! { ::naphtha::diesel::pg::Pg });
+    backends
+}
+
+/// Returns a `match` on the given `AnyConnection` reference that executes
+/// `body` for every enabled backend, with `c` being a reference to the
+/// connection of the backend.
+fn dispatch(
+    connection: ::proc_macro2::TokenStream,
+    body: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    let mut arms = vec![];
+    #[cfg(feature = "sqlite")]
+    arms.push(quote! { ::naphtha::AnyConnection::Sqlite(c) => { #body } });
+    #[cfg(feature = "mysql")]
+    arms.push(quote! { ::naphtha::AnyConnection::Mysql(c) => { #body } });
+    #[cfg(feature = "pg")]
+    arms.push(quote! { ::naphtha::AnyConnection::Pg(c) => { #body } });
+    quote! {
+        match #connection {
+            #(#arms)*
+        }
+    }
+}
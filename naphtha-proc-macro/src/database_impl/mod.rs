@@ -1,6 +1,34 @@
-#[cfg(feature = "mysql")]
-pub(crate) mod mysql;
-#[cfg(feature = "pg")]
-pub(crate) mod pg;
-#[cfg(feature = "sqlite")]
-pub(crate) mod sqlite;
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+pub(crate) mod any;
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+mod model;
+
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+pub(crate) use model::{impl_connection, Connection};
+
+/// Returns the connections the operations of models are implemented for, the
+/// one of every enabled backend and the `AnyConnection`.
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+pub(crate) fn connections() -> Vec<Connection> {
+    let mut connections = vec![];
+    #[cfg(feature = "sqlite")]
+    connections.push(Connection {
+        ty: quote::quote! { ::naphtha::diesel::SqliteConnection },
+        backends: vec![quote::quote! { ::naphtha::diesel::sqlite::Sqlite }],
+        dispatch: |_, body| body,
+    });
+    #[cfg(feature = "mysql")]
+    connections.push(Connection {
+        ty: quote::quote! { ::naphtha::diesel::MysqlConnection },
+        backends: vec![quote::quote! { ::naphtha::diesel::mysql::Mysql }],
+        dispatch: |_, body| body,
+    });
+    #[cfg(feature = "pg")]
+    connections.push(Connection {
+        ty: quote::quote! { ::naphtha::diesel::PgConnection },
+        backends: vec![quote::quote! { ::naphtha::diesel::pg::Pg }],
+        dispatch: |_, body| body,
+    });
+    connections.push(any::connection());
+    connections
+}
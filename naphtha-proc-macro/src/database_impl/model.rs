@@ -0,0 +1,619 @@
+use {
+    crate::params::{Field, KeyStrategy},
+    quote::quote,
+    syn::{parse_quote, DeriveInput},
+};
+
+/// The connection type the operations of a model are implemented for.
+pub(crate) struct Connection {
+    /// The type of the connection, e.g. `::naphtha::diesel::SqliteConnection`.
+    pub(crate) ty: ::proc_macro2::TokenStream,
+    /// The diesel backends the connection may be using.
+    pub(crate) backends: Vec<::proc_macro2::TokenStream>,
+    /// Wraps a statement that uses `c` as connection of its backend, so that
+    /// it is executed on the connection given as first argument.
+    pub(crate) dispatch: fn(
+        ::proc_macro2::TokenStream,
+        ::proc_macro2::TokenStream,
+    ) -> ::proc_macro2::TokenStream,
+}
+
+/// Implements `DatabaseModelModifier` and the query trait of the model for
+/// the given connection.
+pub(crate) fn impl_connection(
+    ast: &DeriveInput,
+    params: &crate::params::Params,
+    fields: &[Field],
+    connection: &Connection,
+) -> ::proc_macro2::TokenStream {
+    let database_modifier =
+        impl_database_modifier(ast, params, fields, connection);
+    let query_by_property =
+        impl_query_by_property(ast, params, fields, connection);
+    quote! {
+        #database_modifier
+        #query_by_property
+    }
+}
+
+fn impl_database_modifier(
+    ast: &DeriveInput,
+    params: &crate::params::Params,
+    fields: &[Field],
+    connection: &Connection,
+) -> ::proc_macro2::TokenStream {
+    let connection_type = &connection.ty;
+    let name = &ast.ident;
+
+    let insert_properties = generate_insert_properties(params, fields);
+    let record_rows = crate::instrument::map_record_rows_affected();
+    let table = params.table_path();
+    let insert_statement = crate::instrument::run_statement(
+        ast,
+        quote! {
+            ::naphtha::diesel::insert_into(#table::table)
+                .values((#insert_properties))
+        },
+        quote! { query.execute(&*c) },
+    );
+
+    // Primary keys assigned by the client are inserted as given.
+    let (check_primary_key, insert_query) = if params.key_strategy
+        == KeyStrategy::Client
+    {
+        (
+            quote! {},
+            quote! {
+                #insert_statement #record_rows?;
+                Ok(self.primary_key())
+            },
+        )
+    } else {
+        (
+            quote! {
+                // preventing duplicate insertion if default primary key gets
+                // changed on database insertion.
+                if self.primary_key() != Self::default_primary_key() {
+                    return Err(::naphtha::anyhow::anyhow!("Could not update primary key!"));
+                }
+            },
+            quote! {
+                #insert_statement #record_rows?;
+                #table::table.select(#table::table.primary_key())
+                    .order(#table::table.primary_key().desc())
+                    .first(&*c)
+            },
+        )
+    };
+    let insert_query =
+        crate::temporal::archive_insert(ast, params, fields, insert_query);
+    let insert_query = crate::audit::audit_insert(params, insert_query);
+
+    let transaction = (connection.dispatch)(
+        quote! { &*c },
+        quote! {
+            c.transaction::<_, ::naphtha::diesel::result::Error, _>(|| {
+                #insert_query
+            })
+        },
+    );
+    let update_statement = crate::dirty::update_statement(
+        ast,
+        params,
+        fields,
+        crate::instrument::run_statement(
+            ast,
+            quote! { ::naphtha::diesel::update(&*self).set(&*self) },
+            // save_changes executes the same update
+            quote! {{
+                let _ = query;
+                self.save_changes::<Self>(&*c)
+            }},
+        ),
+    );
+    let save_changes = (connection.dispatch)(
+        quote! { &*c },
+        crate::audit::audit_update(
+            params,
+            crate::temporal::archive(ast, params, fields, update_statement),
+        ),
+    );
+    let delete_statement = crate::instrument::run_statement(
+        ast,
+        quote! {
+            ::naphtha::diesel::delete(#table::table.find(self.primary_key()))
+        },
+        quote! { query.execute(&*c) },
+    );
+    let delete = (connection.dispatch)(
+        quote! { &*c },
+        crate::audit::audit_remove(
+            params,
+            crate::temporal::archive(
+                ast,
+                params,
+                fields,
+                quote! { #delete_statement #record_rows },
+            ),
+        ),
+    );
+    let read_actor = crate::audit::read_actor(params);
+    let publish_inserted = crate::events::publish(params, "Inserted");
+    let publish_updated = crate::events::publish(params, "Updated");
+    let (removed_rows, publish_removed) =
+        crate::events::publish_removed(params);
+
+    let enter_insert =
+        crate::instrument::enter_operation("insert", ast, params);
+    let enter_update =
+        crate::instrument::enter_operation("update", ast, params);
+    let enter_remove =
+        crate::instrument::enter_operation("remove", ast, params);
+    let enter_reload =
+        crate::instrument::enter_operation("reload", ast, params);
+    let record_primary_key = crate::instrument::record_primary_key(quote! {
+        ::naphtha::DatabaseModel::primary_key(self)
+    });
+    let record_updated = crate::instrument::record_rows_affected(quote! { 1 });
+    let record_error = crate::instrument::record_error(quote! { msg });
+    let describe_model = params.describe_model();
+    let record_success = crate::instrument::record_success();
+    let skip_clean = crate::dirty::skip_clean(fields, &record_success);
+    let mark_clean = crate::dirty::mark_clean(fields);
+    let insert_failed = operation_failed(
+        "insert",
+        "Failed inserting entity",
+        &record_error,
+        &describe_model,
+    );
+    let update_failed = operation_failed(
+        "update",
+        "Failed updating entity",
+        &record_error,
+        &describe_model,
+    );
+    let remove_failed = operation_failed(
+        "remove",
+        "Could not remove model from database",
+        &record_error,
+        &describe_model,
+    );
+    let reload_failed = operation_failed(
+        "reload",
+        "Failed reloading entity",
+        &record_error,
+        &describe_model,
+    );
+
+    let reload_statement = (connection.dispatch)(
+        quote! { &*c },
+        crate::instrument::run_statement(
+            ast,
+            quote! { #table::table.find(self.primary_key()) },
+            quote! { query.get_result::<Self>(&*c) },
+        ),
+    );
+    let reloaded = fields.iter().filter(|f| !f.skip || f.snapshot).map(|f| {
+        let ident = &f.ident;
+        quote! { self.#ident = model.#ident; }
+    });
+    let reload_on_insert = match params.reload_on_insert {
+        true => quote! {
+            if let Err(msg) = ::naphtha::DatabaseModelModifier::reload(self, conn) {
+                #record_error
+                return Err(msg);
+            }
+        },
+        false => quote! {},
+    };
+
+    let mut bounds = vec![parse_quote! {
+        Self: ::naphtha::DatabaseUpdateHandler<#connection_type>
+            + ::naphtha::DatabaseInsertHandler<#connection_type>
+            + ::naphtha::DatabaseRemoveHandler<#connection_type>
+    }];
+    bounds.extend(params.describe_bound());
+    bounds.extend(crate::audit::bounds(params));
+    bounds.extend(crate::events::bounds(params));
+    bounds.extend(crate::dirty::bounds(fields));
+    for backend in connection.backends.iter() {
+        bounds.extend(crate::generics::field_bounds(
+            &ast.generics,
+            params,
+            fields,
+            backend,
+        ));
+    }
+    let generics = crate::generics::extend(&ast.generics, vec![], bounds);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::naphtha::DatabaseModelModifier<#connection_type> for #name #ty_generics
+        #where_clause
+        {
+            #[track_caller]
+            fn insert(
+                &mut self,
+                conn: &::naphtha::DatabaseConnection<#connection_type>
+                ) -> ::naphtha::anyhow::Result<()> {
+                use {
+                    ::naphtha::anyhow::Context,
+                    ::naphtha::{log, DatabaseModel, diesel::{Connection, RunQueryDsl, ExpressionMethods, Table, QueryDsl}},
+                };
+                #enter_insert
+                #check_primary_key
+                self.pre_insert(conn);
+                #read_actor
+                let res_id = match conn.write(|c| #transaction) {
+                    Ok(v) => v,
+                    Err(msg) => {
+                        #insert_failed
+                    }
+                };
+                self.set_primary_key(&res_id);
+                #mark_clean
+                #record_primary_key
+                #reload_on_insert
+                #publish_inserted
+                self.post_insert(conn);
+                #record_success
+                Ok(())
+            }
+
+            #[track_caller]
+            fn update(&mut self, conn: &::naphtha::DatabaseConnection<#connection_type>) -> ::naphtha::anyhow::Result<()> {
+                use ::naphtha::{diesel::SaveChangesDsl, log};
+                #enter_update
+                #record_primary_key
+                self.pre_update(conn);
+                #skip_clean
+                #read_actor
+                match conn.write(|c| #save_changes) {
+                    Ok(_) => {
+                        #mark_clean
+                        #record_updated
+                        #publish_updated
+                    }
+                    Err(msg) => {
+                        #update_failed
+                    }
+                };
+                self.post_update(conn);
+                #record_success
+                Ok(())
+            }
+
+            #[track_caller]
+            fn remove(&mut self, conn: &::naphtha::DatabaseConnection<#connection_type>) -> ::naphtha::anyhow::Result<()> {
+                use {
+                    ::naphtha::{log::{self, info}, DatabaseModel, diesel::{RunQueryDsl, QueryDsl}},
+                };
+                #enter_remove
+                #record_primary_key
+                self.pre_remove(conn);
+                #read_actor
+                // removing is idempotent, so it is retried on broken connections
+                match conn.write_idempotent(|c| #delete) {
+                    Ok(#removed_rows) => {
+                        #[cfg(debug_assertions)]
+                        info!("Removed entity with primary key {:?} from database!", self.primary_key());
+                        #publish_removed
+                    },
+                    Err(msg) => {
+                        #remove_failed
+                    }
+                };
+                self.post_remove(conn);
+                #record_success
+                Ok(())
+            }
+
+            #[track_caller]
+            fn reload(&mut self, conn: &::naphtha::DatabaseConnection<#connection_type>) -> ::naphtha::anyhow::Result<()> {
+                use ::naphtha::{log, DatabaseModel, diesel::{RunQueryDsl, QueryDsl}};
+                #enter_reload
+                #record_primary_key
+                // the primary contains the latest writes
+                match conn.primary().read::<Self, _>(|c| #reload_statement) {
+                    Ok(model) => {
+                        #(#reloaded)*
+                    }
+                    Err(msg) => {
+                        #reload_failed
+                    }
+                };
+                #record_success
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the handling of an error `msg` of the given operation, which is
+/// recorded, logged and returned with a message describing the kind of error.
+fn operation_failed(
+    operation: &str,
+    query_failed: &str,
+    record_error: &::proc_macro2::TokenStream,
+    describe_model: &::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    let query_failed = format!("{}:\nError: {{}}\n{{}}", query_failed);
+    let lock_timeout = format!(
+        "Timed out waiting for the lock on DatabaseModifier::{} for model:\nError: {{}}\n{{}}",
+        operation
+    );
+    let lock_failed = format!(
+        "Could not aquire lock on DatabaseModifier::{} for model:\nError: {{}}\n{{}}",
+        operation
+    );
+    quote! {
+        let message = match &msg {
+            ::naphtha::ConnectionError::Query(_) => {
+                format!(#query_failed, msg, #describe_model)
+            }
+            ::naphtha::ConnectionError::LockTimeout(_) => {
+                format!(#lock_timeout, msg, #describe_model)
+            }
+            ::naphtha::ConnectionError::Lock(_) => {
+                format!(#lock_failed, msg, #describe_model)
+            }
+        };
+        #record_error
+        log::error!("{}", message);
+        return Err(::naphtha::anyhow::Error::new(msg).context(message));
+    }
+}
+
+fn generate_insert_properties(
+    params: &crate::params::Params,
+    fields: &[Field],
+) -> ::proc_macro2::TokenStream {
+    let mut collected_properties = quote! {};
+    for field in fields.iter().filter(|f| !f.skip) {
+        let fieldname = &field.ident;
+        if params.key_strategy == KeyStrategy::Database
+            && params.is_primary_key(fieldname)
+        {
+            // Primary must not be set during insertion.
+            continue;
+        }
+        let column = params.column_path(field);
+        collected_properties = quote! {
+            #collected_properties
+            #column.eq(&self.#fieldname),
+        };
+    }
+    collected_properties
+}
+
+fn impl_query_by_property(
+    ast: &::syn::DeriveInput,
+    params: &crate::params::Params,
+    fields: &[Field],
+    connection: &Connection,
+) -> ::proc_macro2::TokenStream {
+    let connection_type = &connection.ty;
+    let name = &ast.ident;
+    let table = params.table_path();
+
+    let mut queries = quote! {};
+    for field in fields.iter().filter(|f| !f.skip) {
+        let fieldname = &field.ident;
+        let column = params.column_path(field);
+        let (return_type, limit, diesel_query_fn) =
+            match &fieldname.to_string()[..] {
+                "updated_at" => continue,
+                _ => (quote! { Vec<Self> }, quote! {}, quote! { load }),
+            };
+        let (return_type, limit, diesel_query_fn) =
+            if params.primary_key == [fieldname.to_string()] {
+                (quote! { Self }, quote! { .limit(1) }, quote! { get_result })
+            } else {
+                (return_type, limit, diesel_query_fn)
+            };
+        let function_name = ::proc_macro2::Ident::new(
+            &format!("query_by_{}", fieldname).to_lowercase(),
+            ::proc_macro2::Span::call_site(),
+        );
+        let fieldtype = &field.ty;
+        let load = (connection.dispatch)(
+            quote! { c },
+            crate::instrument::run_statement(
+                ast,
+                quote! { #table::table.filter(#column.eq(property))#limit },
+                quote! { query.#diesel_query_fn::<Self>(&*c) },
+            ),
+        );
+        let enter_operation = crate::instrument::enter_operation(
+            &function_name.to_string(),
+            ast,
+            params,
+        );
+        let record_primary_key =
+            if params.primary_key == [fieldname.to_string()] {
+                crate::instrument::record_primary_key(quote! { property })
+            } else {
+                quote! {}
+            };
+        let record_result = crate::instrument::map_record_result();
+        let query = quote! {
+                /// Queries the database by the given #fieldname.
+                #[track_caller]
+                fn #function_name(conn: &::naphtha::DatabaseConnection<#connection_type>, property: &#fieldtype)
+                    -> Result<#return_type, ::naphtha::ConnectionError> {
+                    use ::naphtha::diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+                    #enter_operation
+                    #record_primary_key
+                    conn.read::<#return_type, _>(|c| {
+                        #load
+                    })#record_result
+                }
+        };
+        queries = quote! {
+            #queries
+            #query
+        };
+    }
+
+    let query_by_primary_keys =
+        impl_query_by_primary_keys(ast, params, fields, connection);
+    let temporal_queries = crate::temporal::impl_queries(
+        ast,
+        params,
+        fields,
+        connection.ty.clone(),
+        |body| (connection.dispatch)(quote! { c }, body),
+    );
+
+    let bounds = connection
+        .backends
+        .iter()
+        .flat_map(|backend| {
+            crate::generics::field_bounds(
+                &ast.generics,
+                params,
+                fields,
+                backend,
+            )
+        })
+        .collect();
+    let generics = crate::generics::extend(&ast.generics, vec![], bounds);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+    let arguments = crate::generics::arguments(&ast.generics);
+    let trait_name = crate::database_traits::query_trait_name(name);
+
+    quote! {
+        impl #impl_generics #trait_name<#(#arguments,)* #connection_type> for #name #ty_generics
+        #where_clause
+        {
+            /// The error type for this implementation.
+            type Error = ::naphtha::ConnectionError;
+            #queries
+            #query_by_primary_keys
+            #temporal_queries
+        }
+    }
+}
+
+fn impl_query_by_primary_keys(
+    ast: &::syn::DeriveInput,
+    params: &crate::params::Params,
+    fields: &[Field],
+    connection: &Connection,
+) -> ::proc_macro2::TokenStream {
+    let connection_type = &connection.ty;
+    let table = params.table_path();
+    let record_result = crate::instrument::map_record_result();
+
+    let key_fields = params.primary_key_fields(fields);
+    if params.is_composite_key() {
+        let key_types = key_fields.iter().map(|f| &f.ty);
+        let key_type = quote! { (#(#key_types,)*) };
+        let key_filter = key_fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let column = params.column_path(f);
+                let index = ::syn::Index::from(i);
+                quote! { #column.eq(&primary_key.#index) }
+            })
+            .reduce(|filter, next| quote! { #filter.and(#next) });
+        let load = (connection.dispatch)(
+            quote! { c },
+            crate::instrument::run_statement(
+                ast,
+                quote! { #table::table.filter(#key_filter).limit(1) },
+                quote! { query.get_result::<Self>(&*c) },
+            ),
+        );
+        let load_all = (connection.dispatch)(
+            quote! { c },
+            crate::instrument::run_statement(
+                ast,
+                quote! {{
+                    let mut query = #table::table.into_boxed();
+                    for primary_key in primary_keys {
+                        query = query.or_filter(#key_filter);
+                    }
+                    query
+                }},
+                quote! { query.load::<Self>(&*c) },
+            ),
+        );
+        let enter_key = crate::instrument::enter_operation(
+            "query_by_primary_key",
+            ast,
+            params,
+        );
+        let record_primary_key =
+            crate::instrument::record_primary_key(quote! { primary_key });
+        let enter_keys = crate::instrument::enter_operation(
+            "query_by_primary_keys",
+            ast,
+            params,
+        );
+        return quote! {
+                /// Queries the database by the given composite primary key.
+                #[track_caller]
+                fn query_by_primary_key(conn: &::naphtha::DatabaseConnection<#connection_type>, primary_key: &#key_type)
+                    -> Result<Self, ::naphtha::ConnectionError> {
+                    use ::naphtha::diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, RunQueryDsl};
+                    #enter_key
+                    #record_primary_key
+                    conn.read::<Self, _>(|c| {
+                        #load
+                    })#record_result
+                }
+
+                /// Queries the database for all given composite primary keys.
+                #[track_caller]
+                fn query_by_primary_keys(conn: &::naphtha::DatabaseConnection<#connection_type>, primary_keys: &[#key_type])
+                    -> Result<Vec<Self>, ::naphtha::ConnectionError> {
+                    use ::naphtha::diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, RunQueryDsl};
+                    if primary_keys.is_empty() {
+                        return Ok(vec![]);
+                    }
+                    #enter_keys
+                    conn.read::<Vec<Self>, _>(|c| {
+                        #load_all
+                    })#record_result
+                }
+        };
+    }
+
+    let field = key_fields[0];
+    let fieldtype = &field.ty;
+    let function_name = ::proc_macro2::Ident::new(
+        &format!("query_by_{}s", field.ident).to_lowercase(),
+        ::proc_macro2::Span::call_site(),
+    );
+    let load = (connection.dispatch)(
+        quote! { c },
+        crate::instrument::run_statement(
+            ast,
+            quote! {
+                #table::table.filter(#table::table.primary_key().eq_any(primary_keys))
+            },
+            quote! { query.load::<Self>(&*c) },
+        ),
+    );
+    let enter_operation = crate::instrument::enter_operation(
+        &function_name.to_string(),
+        ast,
+        params,
+    );
+    quote! {
+            /// Queries the database for all given primary keys.
+            #[track_caller]
+            fn #function_name(conn: &::naphtha::DatabaseConnection<#connection_type>, primary_keys: &[#fieldtype])
+                -> Result<Vec<Self>, ::naphtha::ConnectionError> {
+                use ::naphtha::diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, Table};
+                #enter_operation
+                conn.read::<Vec<Self>, _>(|c| {
+                    #load
+                })#record_result
+            }
+    }
+}
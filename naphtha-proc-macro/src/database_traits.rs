@@ -1,28 +1,25 @@
 use quote::quote;
 
+/// Returns the name of the query trait of the given model, e.g. `PersonQueries`
+/// for `Person`. Every model gets its own trait, so several models can be
+/// defined in the same module.
+pub(crate) fn query_trait_name(name: &::syn::Ident) -> ::syn::Ident {
+    ::syn::Ident::new(&format!("{}Queries", name), name.span())
+}
+
 pub fn impl_trait_query_by_properties(
     ast: &::syn::DeriveInput,
     params: &crate::params::Params,
+    fields: &[crate::params::Field],
 ) -> ::proc_macro2::TokenStream {
-    let data = match &ast.data {
-        ::syn::Data::Struct(data) => data,
-        _ => {
-            // return no code if it is not a struct
-            return quote! {};
-        }
-    };
-
     let mut queries = quote! {};
-    for field in data.fields.iter() {
-        if field.ident.is_none() {
-            continue;
-        }
-        let fieldname = field.ident.as_ref().unwrap();
+    for field in fields.iter().filter(|f| !f.skip) {
+        let fieldname = &field.ident;
         let return_type = match &fieldname.to_string()[..] {
             "updated_at" => continue,
             _ => quote! { Vec<Self> },
         };
-        let return_type = if fieldname.to_string()[..] == params.primary_key {
+        let return_type = if params.primary_key == [fieldname.to_string()] {
             quote! { Self }
         } else {
             return_type
@@ -45,56 +42,61 @@ pub fn impl_trait_query_by_properties(
         };
     }
 
-    let query_by_primary_keys = impl_trait_query_by_primary_keys(ast, params);
+    let query_by_primary_keys =
+        impl_trait_query_by_primary_keys(params, fields);
+    let temporal_queries = crate::temporal::trait_queries(params, fields);
+
+    let generics = crate::generics::extend(
+        &ast.generics,
+        vec![::syn::parse_quote!(DB)],
+        vec![::syn::parse_quote!(Self: Sized)],
+    );
+    let (trait_generics, _, where_clause) = generics.split_for_impl();
+    let vis = &ast.vis;
+    let trait_name = query_trait_name(&ast.ident);
 
     quote! {
         /// Queries the model by the given property. Returns only those with an
         /// exact match.
-        pub trait QueryByProperties<DB>
-            where
-                Self: Sized
+        #vis trait #trait_name #trait_generics
+            #where_clause
         {
             /// The error type for this implementation.
             type Error;
             #queries
             #query_by_primary_keys
+            #temporal_queries
         }
     }
 }
 
 fn impl_trait_query_by_primary_keys(
-    ast: &::syn::DeriveInput,
     params: &crate::params::Params,
+    fields: &[crate::params::Field],
 ) -> ::proc_macro2::TokenStream {
-    let data = match &ast.data {
-        ::syn::Data::Struct(data) => data,
-        _ => {
-            // return only defaults if it is not a struct
-            return quote! {};
-        }
-    };
-
-    let mut query = quote! {};
-    for field in data.fields.iter() {
-        if field.ident.is_none() {
-            continue;
-        }
-        let fieldname = field.ident.as_ref().unwrap();
-        if fieldname.to_string()[..] != params.primary_key {
-            continue;
-        }
-        let fieldtype = &field.ty;
-        let function_name = ::proc_macro2::Ident::new(
-            &format!("query_by_{}s", &params.primary_key.to_lowercase()),
-            ::proc_macro2::Span::call_site(),
-        );
-        query = quote! {
-                /// Queries the database by the given #fieldname.
-                fn #function_name(conn: &::naphtha::DatabaseConnection<DB>, primary_keys: &[#fieldtype])
+    let key_fields = params.primary_key_fields(fields);
+    if params.is_composite_key() {
+        let key_types = key_fields.iter().map(|f| &f.ty);
+        let key_type = quote! { (#(#key_types,)*) };
+        return quote! {
+                /// Queries the database by the given composite primary key.
+                fn query_by_primary_key(conn: &::naphtha::DatabaseConnection<DB>, primary_key: &#key_type)
+                    -> Result<Self, Self::Error>;
+                /// Queries the database for all given composite primary keys.
+                fn query_by_primary_keys(conn: &::naphtha::DatabaseConnection<DB>, primary_keys: &[#key_type])
                     -> Result<Vec<Self>, Self::Error>;
         };
-        break;
     }
 
-    query
+    let field = key_fields[0];
+    let fieldtype = &field.ty;
+    let function_name = ::proc_macro2::Ident::new(
+        &format!("query_by_{}s", field.ident).to_lowercase(),
+        ::proc_macro2::Span::call_site(),
+    );
+    quote! {
+            /// Queries the database for all given primary keys.
+            fn #function_name(conn: &::naphtha::DatabaseConnection<DB>, primary_keys: &[#fieldtype])
+                -> Result<Vec<Self>, Self::Error>;
+    }
 }
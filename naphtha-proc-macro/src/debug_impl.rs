@@ -0,0 +1,52 @@
+use {
+    crate::params::Field,
+    quote::quote,
+    syn::{parse_quote, DeriveInput},
+};
+
+/// Implements [Debug](std::fmt::Debug) for the model like the derive macro,
+/// but prints `<redacted>` instead of the value of members marked with
+/// `#[naphtha(sensitive)]`.
+pub(crate) fn impl_debug(
+    ast: &DeriveInput,
+    fields: &[Field],
+) -> ::proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let name_str = name.to_string();
+
+    let members = fields.iter().map(|f| {
+        let ident = &f.ident;
+        let ident_str = ident.to_string();
+        match f.sensitive {
+            true => quote! {
+                .field(#ident_str, &format_args!("<redacted>"))
+            },
+            false => quote! { .field(#ident_str, &self.#ident) },
+        }
+    });
+
+    // like the derive macro, every type parameter is required to be Debug
+    let bounds = ast
+        .generics
+        .type_params()
+        .map(|t| {
+            let ident = &t.ident;
+            parse_quote!(#ident: ::std::fmt::Debug)
+        })
+        .collect();
+    let generics = crate::generics::extend(&ast.generics, vec![], bounds);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::std::fmt::Debug for #name #ty_generics
+        #where_clause
+        {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.debug_struct(#name_str)
+                    #(#members)*
+                    .finish()
+            }
+        }
+    }
+}
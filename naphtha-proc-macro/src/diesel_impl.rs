@@ -0,0 +1,264 @@
+use {
+    crate::params::{Association, Field, Params},
+    quote::quote,
+    syn::{parse_quote, DeriveInput},
+};
+
+/// Implements [Queryable](diesel::Queryable) for the model. Members that are
+/// marked with `#[naphtha(skip)]` are not loaded from the database but
+/// initialized with their [Default] value.
+pub(crate) fn impl_queryable(
+    ast: &DeriveInput,
+    fields: &[Field],
+) -> ::proc_macro2::TokenStream {
+    let name = &ast.ident;
+
+    let persisted = fields.iter().filter(|f| !f.skip);
+    let types = persisted.clone().map(|f| &f.ty).collect::<Vec<_>>();
+    let idents = persisted.map(|f| &f.ident).collect::<Vec<_>>();
+    let skipped = fields
+        .iter()
+        .filter(|f| f.skip && !f.snapshot)
+        .map(|f| &f.ident);
+    let capture = crate::dirty::capture(fields);
+
+    let mut bounds = vec![
+        parse_quote!(__DB: ::naphtha::diesel::backend::Backend),
+        parse_quote!((#(#types,)*): ::naphtha::diesel::Queryable<__ST, __DB>),
+    ];
+    bounds.extend(crate::dirty::values_bound(fields));
+    let generics = crate::generics::extend(
+        &ast.generics,
+        vec![parse_quote!(__DB), parse_quote!(__ST)],
+        bounds,
+    );
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::naphtha::diesel::Queryable<__ST, __DB> for #name #ty_generics
+        #where_clause
+        {
+            type Row = <(#(#types,)*) as ::naphtha::diesel::Queryable<__ST, __DB>>::Row;
+
+            fn build(row: Self::Row) -> Self {
+                let (#(#idents,)*) =
+                    <(#(#types,)*) as ::naphtha::diesel::Queryable<__ST, __DB>>::build(row);
+                Self {
+                    #capture
+                    #(#idents,)*
+                    #(#skipped: ::std::default::Default::default(),)*
+                }
+            }
+        }
+    }
+}
+
+/// Implements [AsChangeset](diesel::query_builder::AsChangeset) for a
+/// reference of the model. The primary key and members marked with
+/// `#[naphtha(skip)]` are not part of the changeset. Members of type `Option`
+/// are only updated if they contain a value.
+pub(crate) fn impl_as_changeset(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+) -> ::proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let table = params.table_path();
+
+    let mut changeset_types = vec![];
+    let mut changeset_values = vec![];
+    let mut bounds = vec![];
+    for field in fields
+        .iter()
+        .filter(|f| !f.skip && !params.is_primary_key(&f.ident))
+    {
+        let ident = &field.ident;
+        let column = params.column_path(field);
+        if crate::generics::uses_type_param(&ast.generics, &field.ty) {
+            let value_type = option_inner_type(&field.ty).unwrap_or(&field.ty);
+            let sql_type = quote! {
+                <#column as ::naphtha::diesel::Expression>::SqlType
+            };
+            bounds
+                .push(crate::generics::expression_bound(value_type, &sql_type));
+        }
+        match option_inner_type(&field.ty) {
+            Some(inner) => {
+                changeset_types.push(quote! {
+                    Option<::naphtha::diesel::dsl::Eq<#column, &'__update #inner>>
+                });
+                changeset_values.push(quote! {
+                    self.#ident.as_ref().map(|v| #column.eq(v))
+                });
+            }
+            None => {
+                let ty = &field.ty;
+                changeset_types.push(quote! {
+                    ::naphtha::diesel::dsl::Eq<#column, &'__update #ty>
+                });
+                changeset_values.push(quote! {
+                    #column.eq(&self.#ident)
+                });
+            }
+        }
+    }
+
+    let generics = crate::generics::extend(
+        &ast.generics,
+        vec![parse_quote!('__update)],
+        bounds,
+    );
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::naphtha::diesel::query_builder::AsChangeset for &'__update #name #ty_generics
+        #where_clause
+        {
+            type Target = #table::table;
+            type Changeset = <(#(#changeset_types,)*) as ::naphtha::diesel::query_builder::AsChangeset>::Changeset;
+
+            fn as_changeset(self) -> Self::Changeset {
+                use ::naphtha::diesel::{query_builder::AsChangeset, ExpressionMethods};
+                (#(#changeset_values,)*).as_changeset()
+            }
+        }
+    }
+}
+
+/// Implements [HasTable](diesel::associations::HasTable) for the model and
+/// [Identifiable](diesel::associations::Identifiable) for a reference of it,
+/// using the fully qualified path to the table.
+pub(crate) fn impl_identifiable(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+) -> ::proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let table = params.table_path();
+    let key_fields = params.primary_key_fields(fields);
+    let key_types = key_fields.iter().map(|f| &f.ty);
+    let key_idents = key_fields.iter().map(|f| &f.ident);
+
+    let (impl_generics, ty_generics, where_clause) =
+        ast.generics.split_for_impl();
+    let ref_generics = crate::generics::extend(
+        &ast.generics,
+        vec![parse_quote!('__ident)],
+        vec![],
+    );
+    let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::naphtha::diesel::associations::HasTable for #name #ty_generics
+        #where_clause
+        {
+            type Table = #table::table;
+
+            fn table() -> Self::Table {
+                #table::table
+            }
+        }
+
+        impl #ref_impl_generics ::naphtha::diesel::associations::Identifiable for &'__ident #name #ty_generics
+        #where_clause
+        {
+            type Id = (#(&'__ident #key_types),*);
+
+            fn id(self) -> Self::Id {
+                (#(&self.#key_idents),*)
+            }
+        }
+    }
+}
+
+/// Implements [BelongsTo](diesel::associations::BelongsTo) for every parent
+/// given by a `#[belongs_to(...)]` attribute.
+pub(crate) fn impl_belongs_to(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+    associations: &[Association],
+) -> ::proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    let impls = associations.iter().map(|association| {
+        let parent = &association.parent;
+        let field = association.foreign_key_field(fields);
+        let ident = &field.ident;
+        let column = params.column_path(field);
+
+        // An optional foreign key is used as is, otherwise the key type of the
+        // parent is used, so the parent may also reference the key.
+        let (generics, foreign_key_type, foreign_key) =
+            match option_inner_type(&field.ty) {
+                Some(inner) => (
+                    ast.generics.clone(),
+                    quote! { #inner },
+                    quote! { self.#ident.as_ref() },
+                ),
+                None => {
+                    let ty = &field.ty;
+                    let generics = crate::generics::extend(
+                        &ast.generics,
+                        vec![parse_quote!(__FK)],
+                        vec![
+                            parse_quote!(__FK: ::std::hash::Hash + ::std::cmp::Eq),
+                            parse_quote!(for<'__a> &'__a #ty: ::std::convert::Into<::std::option::Option<&'__a __FK>>),
+                            parse_quote!(for<'__a> &'__a #parent: ::naphtha::diesel::associations::Identifiable<Id = &'__a __FK>),
+                        ],
+                    );
+                    (
+                        generics,
+                        quote! { __FK },
+                        quote! { ::std::convert::Into::into(&self.#ident) },
+                    )
+                }
+            };
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+        quote! {
+            impl #impl_generics ::naphtha::diesel::associations::BelongsTo<#parent> for #name #ty_generics
+            #where_clause
+            {
+                type ForeignKey = #foreign_key_type;
+                type ForeignKeyColumn = #column;
+
+                fn foreign_key(&self) -> ::std::option::Option<&Self::ForeignKey> {
+                    #foreign_key
+                }
+
+                fn foreign_key_column() -> Self::ForeignKeyColumn {
+                    #column
+                }
+            }
+        }
+    });
+
+    quote! {
+        #(#impls)*
+    }
+}
+
+/// Returns the inner type if the given type is an `Option`.
+pub(crate) fn option_inner_type(ty: &::syn::Type) -> Option<&::syn::Type> {
+    use ::syn::{GenericArgument, PathArguments, Type};
+    let segment = match ty {
+        Type::Path(p) if p.qself.is_none() => p.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => {
+            match args.args.first()? {
+                GenericArgument::Type(inner) => Some(inner),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,188 @@
+use {
+    crate::params::{Field, Params},
+    quote::quote,
+    syn::{parse_quote, DeriveInput, WherePredicate},
+};
+
+/// Returns the member holding the snapshot of the model, if there is one.
+pub(crate) fn snapshot_field(fields: &[Field]) -> Option<&Field> {
+    fields.iter().find(|f| f.snapshot)
+}
+
+/// Returns the tuple type of the persisted members captured by the
+/// snapshot.
+fn values_type(fields: &[Field]) -> ::proc_macro2::TokenStream {
+    let types = fields.iter().filter(|f| !f.skip).map(|f| &f.ty);
+    quote! { (#(#types,)*) }
+}
+
+/// Returns the bound required to capture the persisted members, if the model
+/// has a snapshot.
+pub(crate) fn values_bound(fields: &[Field]) -> Option<WherePredicate> {
+    snapshot_field(fields)?;
+    let values = values_type(fields);
+    Some(parse_quote! {
+        #values: Clone + PartialEq + Send + Sync + 'static
+    })
+}
+
+/// Returns the initialization of the snapshot member by the persisted
+/// members of the same name, used when the model is loaded.
+pub(crate) fn capture(fields: &[Field]) -> ::proc_macro2::TokenStream {
+    let snapshot = match snapshot_field(fields) {
+        Some(f) => &f.ident,
+        None => return quote! {},
+    };
+    let values = values_type(fields);
+    let idents = fields.iter().filter(|f| !f.skip).map(|f| &f.ident);
+    quote! {
+        #snapshot: ::naphtha::dirty::Snapshot::capture::<#values>(
+            (#(::std::clone::Clone::clone(&#idents),)*)
+        ),
+    }
+}
+
+/// Implements `naphtha::dirty::DirtyTracking` for models having a snapshot.
+pub(crate) fn impl_dirty_tracking(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+) -> ::proc_macro2::TokenStream {
+    let snapshot = match snapshot_field(fields) {
+        Some(f) => &f.ident,
+        None => return quote! {},
+    };
+    let name = &ast.ident;
+    let generics = crate::generics::extend(
+        &ast.generics,
+        vec![],
+        values_bound(fields).into_iter().collect(),
+    );
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    let values = values_type(fields);
+    let persisted = fields.iter().filter(|f| !f.skip).collect::<Vec<_>>();
+    let idents = persisted.iter().map(|f| &f.ident);
+    let (indices, changed): (Vec<_>, Vec<_>) = persisted
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| !params.is_primary_key(&f.ident))
+        .map(|(i, f)| (::syn::Index::from(i), &f.ident))
+        .unzip();
+    let names = changed.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+
+    quote! {
+        impl #impl_generics ::naphtha::dirty::DirtyTracking for #name #ty_generics
+        #where_clause
+        {
+            fn changed_fields(&self) -> Vec<&'static str> {
+                match self.#snapshot.values::<#values>() {
+                    Some(values) => {
+                        let mut changed = vec![];
+                        #(
+                            if values.#indices != self.#changed {
+                                changed.push(#names);
+                            }
+                        )*
+                        changed
+                    }
+                    None => vec![#(#names),*],
+                }
+            }
+
+            fn mark_clean(&mut self) {
+                self.#snapshot = ::naphtha::dirty::Snapshot::capture::<#values>(
+                    (#(::std::clone::Clone::clone(&self.#idents),)*)
+                );
+            }
+        }
+    }
+}
+
+/// Returns the bounds required by the modifier of a model having a
+/// snapshot.
+pub(crate) fn bounds(fields: &[Field]) -> Vec<WherePredicate> {
+    if snapshot_field(fields).is_none() {
+        return vec![];
+    }
+    vec![parse_quote!(Self: ::naphtha::dirty::DirtyTracking)]
+}
+
+/// Returns the statement updating the model `self`. For models having a
+/// snapshot, only the columns of `__naphtha_changed` are set, otherwise all
+/// by the given default statement.
+pub(crate) fn update_statement(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+    default: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if snapshot_field(fields).is_none() {
+        return default;
+    }
+    let table = params.table_path();
+    let changes = fields
+        .iter()
+        .filter(|f| !f.skip && !params.is_primary_key(&f.ident))
+        .map(|f| {
+            let ident = &f.ident;
+            let name = ident.to_string();
+            let column = params.column_path(f);
+            quote! {
+                if __naphtha_changed.contains(&#name) {
+                    Some(::naphtha::diesel::ExpressionMethods::eq(#column, &self.#ident))
+                } else {
+                    None
+                },
+            }
+        });
+    let statement = crate::instrument::run_statement(
+        ast,
+        quote! {
+            ::naphtha::diesel::update(::naphtha::diesel::QueryDsl::find(
+                #table::table,
+                ::naphtha::DatabaseModel::primary_key(&*self),
+            ))
+            .set((#(#changes)*))
+        },
+        quote! { ::naphtha::diesel::RunQueryDsl::execute(query, &*c) },
+    );
+    quote! {
+        // a missing row fails like save_changes
+        #statement.and_then(|rows| match rows {
+            0 => Err(::naphtha::diesel::result::Error::NotFound),
+            rows => Ok(rows),
+        })
+    }
+}
+
+/// Returns the statements ending the update of a model having a snapshot
+/// early if nothing changed, and collecting the changed members as
+/// `__naphtha_changed` otherwise.
+pub(crate) fn skip_clean(
+    fields: &[Field],
+    record_success: &::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if snapshot_field(fields).is_none() {
+        return quote! {};
+    }
+    quote! {
+        let __naphtha_changed = ::naphtha::dirty::DirtyTracking::changed_fields(&*self);
+        if __naphtha_changed.is_empty() {
+            self.post_update(conn);
+            #record_success
+            return Ok(());
+        }
+    }
+}
+
+/// Replaces the snapshot of the model `self` after it has been written.
+pub(crate) fn mark_clean(fields: &[Field]) -> ::proc_macro2::TokenStream {
+    if snapshot_field(fields).is_none() {
+        return quote! {};
+    }
+    quote! {
+        ::naphtha::dirty::DirtyTracking::mark_clean(self);
+    }
+}
@@ -0,0 +1,81 @@
+use {
+    crate::params::Params,
+    quote::quote,
+    syn::{parse_quote, DeriveInput, WherePredicate},
+};
+
+/// Implements `naphtha::events::Observable` for models marked by `events`.
+pub(crate) fn impl_observable(
+    ast: &DeriveInput,
+    params: &Params,
+) -> ::proc_macro2::TokenStream {
+    if !params.events {
+        return quote! {};
+    }
+    let name = &ast.ident;
+    let generics = crate::generics::extend(
+        &ast.generics,
+        vec![],
+        vec![parse_quote!(Self: Clone + Send + 'static)],
+    );
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::naphtha::events::Observable for #name #ty_generics
+        #where_clause
+        {}
+    }
+}
+
+/// Returns the bounds required by the modifier of a model publishing its
+/// changes.
+pub(crate) fn bounds(params: &Params) -> Vec<WherePredicate> {
+    if !params.events {
+        return vec![];
+    }
+    vec![
+        parse_quote!(Self: ::naphtha::events::Observable),
+        parse_quote! {
+            <Self as ::naphtha::DatabaseModel>::PrimaryKey: Clone + Send + 'static
+        },
+    ]
+}
+
+/// Publishes the given event variant, e.g. `Inserted`, for the model `self`
+/// to the subscribers of the connection `conn`.
+pub(crate) fn publish(
+    params: &Params,
+    variant: &str,
+) -> ::proc_macro2::TokenStream {
+    if !params.events {
+        return quote! {};
+    }
+    let variant = ::syn::Ident::new(variant, ::proc_macro2::Span::call_site());
+    quote! {
+        conn.publish(|| ::naphtha::events::ModelEvent::#variant {
+            model: ::std::clone::Clone::clone(&*self),
+            primary_key: ::naphtha::DatabaseModel::primary_key(&*self),
+        });
+    }
+}
+
+/// Publishes the `Removed` event if the removal of the model `self` affected
+/// a row. Returns the pattern binding the number of affected rows and the
+/// statements publishing the event.
+pub(crate) fn publish_removed(
+    params: &Params,
+) -> (::proc_macro2::TokenStream, ::proc_macro2::TokenStream) {
+    if !params.events {
+        return (quote! { _ }, quote! {});
+    }
+    let publish = publish(params, "Removed");
+    (
+        quote! { rows },
+        quote! {
+            if rows > 0 {
+                #publish
+            }
+        },
+    )
+}
@@ -0,0 +1,109 @@
+use {
+    crate::params::{Field, Params},
+    quote::{quote, ToTokens},
+    syn::{parse_quote, GenericParam, Generics, WherePredicate},
+};
+
+/// Returns the generics of the model extended by the given parameters and
+/// where predicates, ready to be used with [Generics::split_for_impl].
+pub(crate) fn extend(
+    generics: &Generics,
+    params: Vec<GenericParam>,
+    predicates: Vec<WherePredicate>,
+) -> Generics {
+    let mut generics = generics.clone();
+    // lifetimes are required to be declared before any other parameter
+    let mut lifetimes = 0;
+    for param in params {
+        match param {
+            GenericParam::Lifetime(_) => {
+                generics.params.insert(lifetimes, param);
+                lifetimes += 1;
+            }
+            _ => generics.params.push(param),
+        }
+    }
+    generics.make_where_clause().predicates.extend(predicates);
+    generics
+}
+
+/// Returns the generic arguments of the model without their bounds, e.g.
+/// `'a, T` for `Person<'a, T: Clone>`.
+pub(crate) fn arguments(
+    generics: &Generics,
+) -> Vec<::proc_macro2::TokenStream> {
+    generics
+        .params
+        .iter()
+        .map(|param| match param {
+            GenericParam::Lifetime(l) => l.lifetime.to_token_stream(),
+            GenericParam::Type(t) => t.ident.to_token_stream(),
+            GenericParam::Const(c) => c.ident.to_token_stream(),
+        })
+        .collect()
+}
+
+/// Returns `true` if the given type contains one of the type parameters of
+/// the model.
+pub(crate) fn uses_type_param(generics: &Generics, ty: &::syn::Type) -> bool {
+    fn contains(
+        tokens: ::proc_macro2::TokenStream,
+        idents: &[&::syn::Ident],
+    ) -> bool {
+        tokens.into_iter().any(|token| match token {
+            ::proc_macro2::TokenTree::Ident(i) => idents.contains(&&i),
+            ::proc_macro2::TokenTree::Group(g) => contains(g.stream(), idents),
+            _ => false,
+        })
+    }
+    let idents = generics.type_params().map(|t| &t.ident).collect::<Vec<_>>();
+    !idents.is_empty() && contains(ty.to_token_stream(), &idents)
+}
+
+/// Returns the bounds required to insert, update and load the members whose
+/// type contains a type parameter of the model on the given backend.
+pub(crate) fn field_bounds(
+    generics: &Generics,
+    params: &Params,
+    fields: &[Field],
+    backend: &::proc_macro2::TokenStream,
+) -> Vec<WherePredicate> {
+    let mut bounds = vec![];
+    for field in fields
+        .iter()
+        .filter(|f| !f.skip && uses_type_param(generics, &f.ty))
+    {
+        let ty = &field.ty;
+        let column = params.column_path(field);
+        let sql_type = quote! {
+            <#column as ::naphtha::diesel::Expression>::SqlType
+        };
+        bounds.push(parse_quote! {
+            #ty: ::naphtha::diesel::serialize::ToSql<#sql_type, #backend>
+                + ::naphtha::diesel::Queryable<#sql_type, #backend>
+        });
+        bounds.push(expression_bound(ty, &sql_type));
+        if let Some(inner) = crate::diesel_impl::option_inner_type(ty) {
+            // used by the changeset
+            bounds.push(parse_quote! {
+                #inner: ::naphtha::diesel::serialize::ToSql<#sql_type, #backend>
+            });
+            bounds.push(expression_bound(inner, &sql_type));
+        }
+    }
+    bounds
+}
+
+/// Returns the bound for a reference of the given type to be used as bound
+/// value in a query.
+pub(crate) fn expression_bound(
+    ty: &::syn::Type,
+    sql_type: &::proc_macro2::TokenStream,
+) -> WherePredicate {
+    parse_quote! {
+        for<'__expr> &'__expr #ty: ::naphtha::diesel::expression::AsExpression<
+            #sql_type,
+            Expression = ::naphtha::diesel::expression::bound::Bound<#sql_type, &'__expr #ty>,
+        >
+    }
+}
@@ -0,0 +1,162 @@
+//! Instrumentation of the generated operations, enabled by the `tracing` and
+//! `metrics` features. All functions return an empty token stream otherwise.
+
+use quote::quote;
+
+/// Creates and enters the span of the given operation and starts measuring
+/// it. The span is exited and the measurement recorded at the end of the
+/// enclosing block, the operation is considered failed unless
+/// [record_success] has been called.
+///
+/// The location of the caller is captured for [run_statement], the function
+/// of the operation requires `#[track_caller]`.
+pub(crate) fn enter_operation(
+    operation: &str,
+    ast: &::syn::DeriveInput,
+    params: &crate::params::Params,
+) -> ::proc_macro2::TokenStream {
+    let model = ast.ident.to_string();
+    let table = &params.table_name;
+    let mut measure = quote! {
+        let __naphtha_location = ::std::panic::Location::caller();
+    };
+    if cfg!(feature = "metrics") {
+        measure = quote! {
+            #measure
+            let mut __naphtha_operation =
+                ::naphtha::instrument::Operation::start(#model, #operation);
+        };
+    }
+    if !cfg!(feature = "tracing") {
+        return measure;
+    }
+    quote! {
+        #measure
+        let __naphtha_span = ::naphtha::tracing::info_span!(
+            target: "naphtha",
+            #operation,
+            model = #model,
+            table = #table,
+            primary_key = ::naphtha::tracing::field::Empty,
+            rows_affected = ::naphtha::tracing::field::Empty,
+            lock_wait_us = ::naphtha::tracing::field::Empty,
+            duration_us = ::naphtha::tracing::field::Empty,
+            error = ::naphtha::tracing::field::Empty,
+        );
+        let __naphtha_entered = __naphtha_span.enter();
+    }
+}
+
+/// Executes the statement built by `query` on the connection `c` by `run`,
+/// which receives the statement as `query`. The statement is logged if it
+/// exceeds the threshold of the `SlowQueryLog` of `conn`. Requires the
+/// operation to be entered by [enter_operation].
+pub(crate) fn run_statement(
+    ast: &::syn::DeriveInput,
+    query: ::proc_macro2::TokenStream,
+    run: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    let model = ast.ident.to_string();
+    quote! {
+        ::naphtha::SlowQueryLog::run(
+            conn.slow_query_log(),
+            #model,
+            __naphtha_location,
+            &*c,
+            || #query,
+            |query| #run,
+        )
+    }
+}
+
+/// Records the given primary key on the current span, requires
+/// [Debug](std::fmt::Debug).
+pub(crate) fn record_primary_key(
+    primary_key: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    record(
+        "primary_key",
+        quote! { ::naphtha::tracing::field::debug(&#primary_key) },
+    )
+}
+
+/// Records the number of affected rows on the current span.
+pub(crate) fn record_rows_affected(
+    rows: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    record("rows_affected", quote! { #rows as u64 })
+}
+
+/// Returns a `.map(...)` call recording the number of affected rows of an
+/// `execute` result on the current span.
+pub(crate) fn map_record_rows_affected() -> ::proc_macro2::TokenStream {
+    if !cfg!(feature = "tracing") {
+        return quote! {};
+    }
+    let record_rows = record_rows_affected(quote! { rows });
+    quote! {
+        .map(|rows| {
+            #record_rows
+            rows
+        })
+    }
+}
+
+/// Records the given error on the current span, requires
+/// [Display](std::fmt::Display).
+pub(crate) fn record_error(
+    error: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    record(
+        "error",
+        quote! { ::naphtha::tracing::field::display(&#error) },
+    )
+}
+
+/// Marks the operation entered by [enter_operation] as succeeded.
+pub(crate) fn record_success() -> ::proc_macro2::TokenStream {
+    if !cfg!(feature = "metrics") {
+        return quote! {};
+    }
+    quote! {
+        __naphtha_operation.succeeded();
+    }
+}
+
+/// Returns `.map(...)` and `.map_err(...)` calls recording the outcome of the
+/// operation entered by [enter_operation].
+pub(crate) fn map_record_result() -> ::proc_macro2::TokenStream {
+    let mut calls = quote! {};
+    if cfg!(feature = "metrics") {
+        let record_success = record_success();
+        calls = quote! {
+            .map(|value| {
+                #record_success
+                value
+            })
+        };
+    }
+    if cfg!(feature = "tracing") {
+        let record_error = record_error(quote! { e });
+        calls = quote! {
+            #calls
+            .map_err(|e| {
+                #record_error
+                e
+            })
+        };
+    }
+    calls
+}
+
+fn record(
+    field: &str,
+    value: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if !cfg!(feature = "tracing") {
+        return quote! {};
+    }
+    quote! {
+        ::naphtha::tracing::Span::current().record(#field, &(#value));
+    }
+}
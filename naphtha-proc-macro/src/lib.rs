@@ -5,106 +5,186 @@ extern crate quote;
 
 use {
     quote::quote,
-    syn::{parse, DeriveInput},
+    syn::{parse_macro_input, AttributeArgs, DeriveInput},
 };
 
+mod audit;
 #[cfg(any(
     feature = "barrel-sqlite",
     feature = "barrel-mysql",
     feature = "barrel-pg"
 ))]
 mod barrel_impl;
+#[cfg(feature = "sqlite")]
+mod changes;
 mod database_impl;
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
 mod database_traits;
-#[allow(dead_code)]
+mod debug_impl;
+mod diesel_impl;
+mod dirty;
+mod events;
+mod generics;
+mod instrument;
 mod params;
+mod serde_impl;
+mod temporal;
+
+/// Parses the attribute parameter, the members and the parents of the model.
+fn parse_model(
+    args: &[::syn::NestedMeta],
+    ast: &DeriveInput,
+) -> ::syn::Result<(params::Params, Vec<params::Field>, Vec<params::Association>)>
+{
+    let params = params::Params::parse(args)?;
+    let fields = params::Field::collect(ast)?;
+    params.validate(&fields)?;
+    let associations = params::Association::collect(ast)?;
+    for association in associations.iter() {
+        association.validate(&fields)?;
+    }
+    Ok((params, fields, associations))
+}
 
 #[proc_macro_attribute]
 pub fn model(
     attr: ::proc_macro::TokenStream,
     item: ::proc_macro::TokenStream,
 ) -> ::proc_macro::TokenStream {
-    let ast: DeriveInput = parse(item).expect(
-        "proc_macro_attribute model: Could not parse TokenStream input!",
-    );
-
-    let params = params::Params::from(attr);
-    let attribute_table_name =
-        format!("#[table_name = \"{}\"]", params.table_name);
-    let attribute_table_name: ::proc_macro2::TokenStream =
-        attribute_table_name.parse().unwrap();
-    let attribute_primary_key =
-        format!("#[primary_key({})]", params.primary_key);
-    let attribute_primary_key: ::proc_macro2::TokenStream =
-        attribute_primary_key.parse().unwrap();
+    let args = parse_macro_input!(attr as AttributeArgs);
+    let mut ast = parse_macro_input!(item as DeriveInput);
+
+    let (params, fields, associations) = match parse_model(&args, &ast) {
+        Ok(v) => v,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    serde_impl::add_attributes(&mut ast, &params, &fields);
+    params::Field::strip_attributes(&mut ast);
+    params::Association::strip_attributes(&mut ast);
+    // Debug is implemented by hand if members need to be redacted
+    let redact = params.derives_debug() && fields.iter().any(|f| f.sensitive);
+    let derive = params
+        .derive
+        .iter()
+        .filter(|path| !redact || !params::Params::is_debug(path))
+        .collect::<Vec<_>>();
+    let impl_debug = match redact {
+        true => debug_impl::impl_debug(&ast, &fields),
+        false => quote! {},
+    };
+
+    // DIESEL TRAITS
+    let impl_identifiable =
+        diesel_impl::impl_identifiable(&ast, &params, &fields);
+    let impl_belongs_to =
+        diesel_impl::impl_belongs_to(&ast, &params, &fields, &associations);
+    let impl_queryable = diesel_impl::impl_queryable(&ast, &fields);
+    let impl_as_changeset =
+        diesel_impl::impl_as_changeset(&ast, &params, &fields);
+
+    // AUDIT LOG
+    let impl_audited = audit::impl_audited(&ast, &params, &fields);
+
+    // DIRTY TRACKING
+    let impl_dirty_tracking =
+        dirty::impl_dirty_tracking(&ast, &params, &fields);
+
+    // SERDE
+    let serde_derive = serde_impl::derive(&params);
+    let impl_json_transfer =
+        serde_impl::impl_json_transfer(&ast, &params, &fields);
+
+    // CHANGE EVENTS
+    let impl_observable = events::impl_observable(&ast, &params);
 
     // QUERY BY PROPERTY TRAIT
     #[cfg(not(any(feature = "sqlite", feature = "mysql", feature = "pg")))]
     let impl_trait_query_by_properties = quote! {};
     #[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
     let impl_trait_query_by_properties =
-        database_traits::impl_trait_query_by_properties(&ast, &params);
+        database_traits::impl_trait_query_by_properties(&ast, &params, &fields);
+
+    // DATABASE CONNECTIONS
+    #[cfg(not(any(feature = "sqlite", feature = "mysql", feature = "pg")))]
+    let impl_connections = quote! {};
+    #[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+    let impl_connections = database_impl::connections()
+        .iter()
+        .map(|connection| {
+            database_impl::impl_connection(&ast, &params, &fields, connection)
+        })
+        .collect::<::proc_macro2::TokenStream>();
 
     // SQLITE
     #[cfg(not(feature = "sqlite"))]
-    let impl_sqlite = quote! {};
+    let impl_query_by_rowid = quote! {};
     #[cfg(feature = "sqlite")]
-    let impl_sqlite = database_impl::sqlite::impl_sqlite(&ast, &params);
+    let impl_query_by_rowid =
+        changes::impl_query_by_rowid(&ast, &params, &fields);
 
     #[cfg(not(feature = "barrel-sqlite"))]
     let impl_barrel_sqlite = quote! {};
     #[cfg(feature = "barrel-sqlite")]
-    let impl_barrel_sqlite = barrel_impl::sqlite::impl_sqlite(&ast);
+    let impl_barrel_sqlite =
+        barrel_impl::sqlite::impl_sqlite(&ast, &params, &fields);
 
     // MYSQL
-    #[cfg(not(feature = "mysql"))]
-    let impl_mysql = quote! {};
-    #[cfg(feature = "mysql")]
-    let impl_mysql = database_impl::mysql::impl_mysql(&ast, &params);
-
     #[cfg(not(feature = "barrel-mysql"))]
     let impl_barrel_mysql = quote! {};
     #[cfg(feature = "barrel-mysql")]
-    let impl_barrel_mysql = barrel_impl::mysql::impl_mysql(&ast);
+    let impl_barrel_mysql =
+        barrel_impl::mysql::impl_mysql(&ast, &params, &fields);
 
     // PostgreSQL
-    #[cfg(not(feature = "pg"))]
-    let impl_pg = quote! {};
-    #[cfg(feature = "pg")]
-    let impl_pg = database_impl::pg::impl_pg(&ast, &params);
     #[cfg(not(feature = "barrel-pg"))]
     let impl_barrel_pg = quote! {};
     #[cfg(feature = "barrel-pg")]
-    let impl_barrel_pg = barrel_impl::pg::impl_pg(&ast);
+    let impl_barrel_pg = barrel_impl::pg::impl_pg(&ast, &params, &fields);
+
+    // ANY CONNECTION
+    #[cfg(not(all(
+        any(feature = "sqlite", feature = "mysql", feature = "pg"),
+        any(
+            feature = "barrel-sqlite",
+            feature = "barrel-mysql",
+            feature = "barrel-pg"
+        )
+    )))]
+    let impl_barrel_any = quote! {};
+    #[cfg(all(
+        any(feature = "sqlite", feature = "mysql", feature = "pg"),
+        any(
+            feature = "barrel-sqlite",
+            feature = "barrel-mysql",
+            feature = "barrel-pg"
+        )
+    ))]
+    let impl_barrel_any = barrel_impl::any::impl_any(&ast, &params, &fields);
 
     let output = quote! {
-        use self::schema::*;
-        #[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
-        use {
-            ::naphtha::diesel::{backend::Backend, prelude::*},
-        };
-
-        #[derive(
-            Debug,
-            Queryable,
-            Identifiable,
-            AsChangeset,
-            Associations
-            )]
-        #attribute_table_name
-        #attribute_primary_key
+        #[derive(#(#derive),*)]
+        #serde_derive
         #ast
 
+        #impl_debug
+        #impl_identifiable
+        #impl_belongs_to
+        #impl_queryable
+        #impl_as_changeset
+        #impl_audited
+        #impl_observable
+        #impl_dirty_tracking
+        #impl_json_transfer
+
         #impl_trait_query_by_properties
 
-        #impl_sqlite
-        #impl_barrel_sqlite
+        #impl_connections
+        #impl_query_by_rowid
 
-        #impl_mysql
+        #impl_barrel_sqlite
         #impl_barrel_mysql
-
-        #impl_pg
         #impl_barrel_pg
+        #impl_barrel_any
     };
 
     ::proc_macro::TokenStream::from(output)
@@ -1,89 +1,683 @@
+use {
+    proc_macro2::{Span, TokenStream},
+    quote::quote,
+    syn::{spanned::Spanned, Error, Lit, Meta, NestedMeta, Result},
+};
+
 /// The input parameter of the attribute.
-#[derive(Debug)]
 pub struct Params {
     pub table_name: String,
-    pub primary_key: String,
+    /// The members forming the primary key, more than one for composite keys.
+    pub primary_key: Vec<String>,
+    /// The location of the `primary_key` value, used for error messages.
+    primary_key_span: Span,
+    /// Defines who assigns the primary key on insertion.
+    pub key_strategy: KeyStrategy,
+    /// The path to the module containing the `table!` definition.
+    pub schema: ::syn::Path,
+    /// The traits that are derived for the model.
+    pub derive: Vec<::syn::Path>,
+    /// Defines how the model is described in error messages.
+    pub log: LogDetail,
+    /// If `true`, all changes of the model are recorded in the audit log.
+    pub audited: bool,
+    /// If `true`, previous versions of the model are kept in a history table.
+    pub temporal: bool,
+    /// If `true`, the committed changes of the model are published to the
+    /// subscribers of the connection.
+    pub events: bool,
+    /// If `true`, the model is reloaded after its insertion, populating the
+    /// values computed by the database.
+    pub reload_on_insert: bool,
+    /// If `true`, `Serialize` and `Deserialize` are derived and the table can
+    /// be exported and imported as JSON.
+    pub serde: bool,
 }
 
-impl Params {
-    const TABLE_NAME: &'static str = "table_name";
-    const PRIMARY_KEY: &'static str = "primary_key";
+/// Defines who assigns the primary key of a new model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStrategy {
+    /// The key is assigned by the database on insertion and read back into
+    /// the model, e.g. auto increment columns.
+    Database,
+    /// The key is assigned by the application and inserted as given, e.g.
+    /// UUIDs or natural keys.
+    Client,
 }
 
-#[derive(PartialEq, Eq)]
-enum ParseState {
-    Identifier,
-    Punct,
-    Literal,
+/// Defines how much of a model is contained in the error messages of the
+/// generated operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogDetail {
+    /// The whole model is logged by its [Debug] representation, members
+    /// marked with `#[naphtha(sensitive)]` are redacted.
+    Model,
+    /// Only the primary key is logged, the model does not need to implement
+    /// [Debug].
+    PrimaryKey,
 }
 
-impl From<::proc_macro::TokenStream> for Params {
-    fn from(attr: ::proc_macro::TokenStream) -> Self {
-        use proc_macro::TokenTree::*;
+impl Params {
+    const TABLE_NAME: &'static str = "table_name";
+    const PRIMARY_KEY: &'static str = "primary_key";
+    const KEY_STRATEGY: &'static str = "key_strategy";
+    const SCHEMA: &'static str = "schema";
+    const DERIVE: &'static str = "derive";
+    const LOG: &'static str = "log";
+    const AUDITED: &'static str = "audited";
+    const TEMPORAL: &'static str = "temporal";
+    const EVENTS: &'static str = "events";
+    const RELOAD_ON_INSERT: &'static str = "reload_on_insert";
+    const SERDE: &'static str = "serde";
+    const PARAMETERS: &'static [&'static str] = &[
+        Self::TABLE_NAME,
+        Self::PRIMARY_KEY,
+        Self::KEY_STRATEGY,
+        Self::SCHEMA,
+        Self::DERIVE,
+        Self::LOG,
+        Self::AUDITED,
+        Self::TEMPORAL,
+        Self::EVENTS,
+        Self::RELOAD_ON_INSERT,
+        Self::SERDE,
+    ];
+
+    /// Parses the arguments given to the `#[model(...)]` attribute.
+    pub fn parse(args: &[NestedMeta]) -> Result<Self> {
         let mut table_name = None;
         let mut primary_key = None;
+        let mut key_strategy = None;
+        let mut schema = None;
+        let mut derive = None;
+        let mut log = None;
+        let mut audited = false;
+        let mut temporal = false;
+        let mut events = false;
+        let mut reload_on_insert = false;
+        let mut serde = false;
 
-        // The expected token
-        let mut parse_state = ParseState::Identifier;
-        // the current identifier (name of the parameter)
-        let mut current_ident: Option<::proc_macro::Ident> = None;
-
-        for a in attr.into_iter() {
-            match a {
-                Ident(ident) => {
-                    if parse_state != ParseState::Identifier {
-                        panic!("Syntax error in parsing Params struct. Expected identifier!");
+        for arg in args {
+            let nv = match arg {
+                NestedMeta::Meta(Meta::NameValue(nv)) => nv,
+                NestedMeta::Meta(Meta::Path(p))
+                    if p.is_ident(Self::AUDITED) =>
+                {
+                    if cfg!(not(feature = "audit")) {
+                        return Err(Error::new(
+                            p.span(),
+                            "The audited parameter requires the audit feature of naphtha!",
+                        ));
+                    }
+                    audited = true;
+                    continue;
+                }
+                NestedMeta::Meta(Meta::Path(p))
+                    if p.is_ident(Self::TEMPORAL) =>
+                {
+                    if cfg!(not(feature = "temporal")) {
+                        return Err(Error::new(
+                            p.span(),
+                            "The temporal parameter requires the temporal feature of naphtha!",
+                        ));
+                    }
+                    temporal = true;
+                    continue;
+                }
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident(Self::EVENTS) => {
+                    events = true;
+                    continue;
+                }
+                NestedMeta::Meta(Meta::Path(p))
+                    if p.is_ident(Self::RELOAD_ON_INSERT) =>
+                {
+                    reload_on_insert = true;
+                    continue;
+                }
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident(Self::SERDE) => {
+                    if cfg!(not(feature = "serde")) {
+                        return Err(Error::new(
+                            p.span(),
+                            "The serde parameter requires the serde feature of naphtha!",
+                        ));
                     }
-                    current_ident = Some(ident);
-                    parse_state = ParseState::Punct;
+                    serde = true;
+                    continue;
+                }
+                _ => {
+                    return Err(Error::new(
+                        arg.span(),
+                        "Expected a parameter like table_name = \"my_table\"",
+                    ))
                 }
-                Punct(punct) => {
-                    if parse_state != ParseState::Punct {
-                        panic!("Syntax error in parsing Params struct. Expected Punctuation like , or =");
+            };
+            let value = match &nv.lit {
+                Lit::Str(s) => s,
+                lit => {
+                    return Err(Error::new(
+                        lit.span(),
+                        "Expected a string literal",
+                    ))
+                }
+            };
+            if nv.path.is_ident(Self::TABLE_NAME) {
+                // the table name is used as identifier in the generated code
+                value.parse::<::syn::Ident>()?;
+                table_name = Some(value.value());
+            } else if nv.path.is_ident(Self::PRIMARY_KEY) {
+                let keys = value
+                    .parse_with(
+                        ::syn::punctuated::Punctuated::<
+                            ::syn::Ident,
+                            ::syn::Token![,],
+                        >::parse_separated_nonempty,
+                    )?
+                    .into_iter()
+                    .map(|k| k.to_string())
+                    .collect::<Vec<_>>();
+                primary_key = Some((keys, value.span()));
+            } else if nv.path.is_ident(Self::KEY_STRATEGY) {
+                key_strategy = match &value.value()[..] {
+                    "database" => Some((KeyStrategy::Database, value.span())),
+                    "client" => Some((KeyStrategy::Client, value.span())),
+                    _ => {
+                        return Err(Error::new(
+                            value.span(),
+                            "Unknown key strategy given! Possible values are: database, client",
+                        ))
+                    }
+                };
+            } else if nv.path.is_ident(Self::SCHEMA) {
+                schema = Some(value.parse::<::syn::Path>()?);
+            } else if nv.path.is_ident(Self::DERIVE) {
+                // an empty list is valid and removes all default derives
+                let traits = value.parse_with(
+                    ::syn::punctuated::Punctuated::<
+                        ::syn::Path,
+                        ::syn::Token![,],
+                    >::parse_terminated,
+                )?;
+                derive = Some(traits.into_iter().collect());
+            } else if nv.path.is_ident(Self::LOG) {
+                log = match &value.value()[..] {
+                    "model" => Some(LogDetail::Model),
+                    "primary_key" => Some(LogDetail::PrimaryKey),
+                    _ => {
+                        return Err(Error::new(
+                            value.span(),
+                            "Unknown log detail given! Possible values are: model, primary_key",
+                        ))
                     }
-                    parse_state = match &punct.as_char().to_string()[..] {
-                        "=" => ParseState::Literal,
-                        "," => ParseState::Identifier,
-                        _ => panic!("Syntax error. Unknown punctuation given: {}\nPossible values are: , or =", punct.as_char()),
-                    };
+                };
+            } else {
+                return Err(Error::new(
+                    nv.path.span(),
+                    format!(
+                        "Unknown parameter '{}' given! Possible values are: {}",
+                        nv.path
+                            .get_ident()
+                            .map(|i| i.to_string())
+                            .unwrap_or_default(),
+                        Self::PARAMETERS.join(", "),
+                    ),
+                ));
+            }
+        }
+
+        let table_name = table_name.ok_or_else(|| {
+            Error::new(
+                Span::call_site(),
+                "Missing parameter table_name. Please add it to the model attribute, e.g. table_name = \"my_new_table\"!",
+            )
+        })?;
+
+        let (primary_key, primary_key_span) = primary_key.ok_or_else(|| {
+            Error::new(
+                Span::call_site(),
+                "Missing parameter primary_key. Please add it to the model attribute, e.g. primary_key = \"id\"!",
+            )
+        })?;
+
+        // Composite primary keys can not be assigned by the database.
+        let key_strategy = match key_strategy {
+            Some((KeyStrategy::Database, span)) if primary_key.len() > 1 => {
+                return Err(Error::new(
+                    span,
+                    "Composite primary keys require the client key strategy!",
+                ))
+            }
+            Some((strategy, _)) => strategy,
+            None if primary_key.len() > 1 => KeyStrategy::Client,
+            None => KeyStrategy::Database,
+        };
+
+        Ok(Params {
+            table_name,
+            primary_key,
+            primary_key_span,
+            key_strategy,
+            schema: schema.unwrap_or_else(|| ::syn::parse_quote!(self::schema)),
+            derive: derive.unwrap_or_else(|| vec![::syn::parse_quote!(Debug)]),
+            log: log.unwrap_or(LogDetail::Model),
+            audited,
+            temporal,
+            events,
+            reload_on_insert,
+            serde,
+        })
+    }
+
+    /// Makes sure that the parameters match the members of the model.
+    pub fn validate(&self, fields: &[Field]) -> Result<()> {
+        for (i, key) in self.primary_key.iter().enumerate() {
+            if self.primary_key[..i].contains(key) {
+                return Err(Error::new(
+                    self.primary_key_span,
+                    format!("The primary key '{}' is given twice!", key),
+                ));
+            }
+            match fields.iter().find(|f| f.ident == key) {
+                Some(f) if f.skip => {
+                    return Err(Error::new(
+                        self.primary_key_span,
+                        format!(
+                            "The primary key '{}' must not be skipped!",
+                            key
+                        ),
+                    ))
+                }
+                Some(_) => (),
+                None => {
+                    return Err(Error::new(
+                        self.primary_key_span,
+                        format!(
+                        "The primary key '{}' is not a member of the model!",
+                        key
+                    ),
+                    ))
+                }
+            }
+        }
+        if let Some(f) = fields.iter().filter(|f| f.snapshot).nth(1) {
+            return Err(Error::new(
+                f.ident.span(),
+                "Only one member can hold the snapshot of the model!",
+            ));
+        }
+        if self.temporal {
+            // these columns are added to the history table
+            let reserved = ["history_id", "valid_from", "valid_to"];
+            if let Some(f) = fields
+                .iter()
+                .find(|f| !f.skip && reserved.iter().any(|r| f.column == r))
+            {
+                return Err(Error::new(
+                    f.column.span(),
+                    format!(
+                        "The column '{}' is reserved for the history table of temporal models!",
+                        f.column
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the name of the table keeping the previous versions of a
+    /// `temporal` model, e.g. `persons_history`.
+    pub fn history_table_name(&self) -> String {
+        format!("{}_history", self.table_name)
+    }
+
+    /// Returns `true` if the given member is part of the primary key.
+    pub fn is_primary_key(&self, ident: &::syn::Ident) -> bool {
+        self.primary_key.iter().any(|k| ident == k)
+    }
+
+    /// Returns `true` if the primary key consists of more than one member.
+    pub fn is_composite_key(&self) -> bool {
+        self.primary_key.len() > 1
+    }
+
+    /// Returns the fully qualified path to the table module of the model,
+    /// e.g. `self::schema::persons`.
+    pub fn table_path(&self) -> TokenStream {
+        let schema = &self.schema;
+        let table_name =
+            ::proc_macro2::Ident::new(&self.table_name, Span::call_site());
+        quote! { #schema::#table_name }
+    }
+
+    /// Returns the fully qualified path to the column of the given member.
+    pub fn column_path(&self, field: &Field) -> TokenStream {
+        let table = self.table_path();
+        let column = &field.column;
+        quote! { #table::#column }
+    }
+
+    /// Returns an expression of type `String` describing the model `self` in
+    /// the error messages of the generated operations.
+    pub fn describe_model(&self) -> TokenStream {
+        match self.log {
+            LogDetail::Model => quote! { format!("Model:\n{:#?}", self) },
+            LogDetail::PrimaryKey => quote! {
+                format!(
+                    "Primary key: {:?}",
+                    ::naphtha::DatabaseModel::primary_key(self)
+                )
+            },
+        }
+    }
+
+    /// Returns the bound required by [describe_model](Self::describe_model).
+    pub fn describe_bound(&self) -> Option<::syn::WherePredicate> {
+        match self.log {
+            LogDetail::Model => {
+                Some(::syn::parse_quote!(Self: ::std::fmt::Debug))
+            }
+            LogDetail::PrimaryKey => None,
+        }
+    }
+
+    /// Returns `true` if `Debug` is one of the traits derived for the model.
+    pub fn derives_debug(&self) -> bool {
+        self.derive.iter().any(Self::is_debug)
+    }
+
+    /// Returns `true` if the given derived trait is `Debug`.
+    pub fn is_debug(path: &::syn::Path) -> bool {
+        matches!(path.segments.last(), Some(s) if s.ident == "Debug")
+    }
+
+    /// Returns the members forming the primary key in the order they are
+    /// given in the `primary_key` parameter.
+    pub fn primary_key_fields<'a>(
+        &self,
+        fields: &'a [Field],
+    ) -> Vec<&'a Field> {
+        self.primary_key
+            .iter()
+            .filter_map(|k| fields.iter().find(|f| f.ident == k))
+            .collect()
+    }
+}
+
+/// A named member of the model `struct` including its field level parameters
+/// given by the `#[naphtha(...)]` attribute.
+pub struct Field {
+    /// The name of the member.
+    pub ident: ::syn::Ident,
+    /// The type of the member.
+    pub ty: ::syn::Type,
+    /// The column name on the database table, defaults to the member name.
+    pub column: ::syn::Ident,
+    /// If `true`, the member is not persisted and populated by [Default] when
+    /// loaded from the database.
+    pub skip: bool,
+    /// If `true`, the value of the member is redacted in the [Debug]
+    /// representation and the error messages of the model.
+    pub sensitive: bool,
+    /// If `true`, the member holds the `naphtha::dirty::Snapshot` of the
+    /// loaded values. It is not persisted, so [skip](Field::skip) is set.
+    pub snapshot: bool,
+}
+
+impl Field {
+    const ATTRIBUTE: &'static str = "naphtha";
+    const COLUMN: &'static str = "column";
+    const SKIP: &'static str = "skip";
+    const SENSITIVE: &'static str = "sensitive";
+    const SNAPSHOT: &'static str = "snapshot";
+
+    /// Collects all named members of the given `struct`.
+    pub fn collect(ast: &::syn::DeriveInput) -> Result<Vec<Self>> {
+        let data =
+            match &ast.data {
+                ::syn::Data::Struct(data) => data,
+                _ => return Err(Error::new(
+                    ast.ident.span(),
+                    "Other data formats than \"struct\" is not supported yet!",
+                )),
+            };
+        match &data.fields {
+            ::syn::Fields::Named(fields) => {
+                fields.named.iter().map(Self::parse).collect()
+            }
+            fields => Err(Error::new(
+                fields.span(),
+                "Only structs with named members are supported!",
+            )),
+        }
+    }
+
+    /// Returns `true` if the given attribute is a `#[naphtha(...)]` attribute.
+    pub fn is_naphtha_attribute(attr: &::syn::Attribute) -> bool {
+        attr.path.is_ident(Self::ATTRIBUTE)
+    }
+
+    /// Removes all `#[naphtha(...)]` attributes from the members of the given
+    /// `struct`, as they are not known to the compiler.
+    pub fn strip_attributes(ast: &mut ::syn::DeriveInput) {
+        if let ::syn::Data::Struct(data) = &mut ast.data {
+            for field in data.fields.iter_mut() {
+                field.attrs.retain(|a| !Self::is_naphtha_attribute(a));
+            }
+        }
+    }
+
+    /// Parses the `#[naphtha(...)]` attributes of the given member.
+    fn parse(field: &::syn::Field) -> Result<Self> {
+        let ident = match &field.ident {
+            Some(i) => i.clone(),
+            None => {
+                return Err(Error::new(
+                    field.span(),
+                    "Only named struct members are supported!",
+                ))
+            }
+        };
+        let mut column = None;
+        let mut skip = false;
+        let mut sensitive = false;
+        let mut snapshot = false;
+
+        for attr in field.attrs.iter().filter(|a| Self::is_naphtha_attribute(a))
+        {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => {
+                    return Err(Error::new(
+                        meta.span(),
+                        "Expected e.g. #[naphtha(column = \"my_column\")]",
+                    ))
                 }
-                Literal(literal) => {
-                    if parse_state != ParseState::Literal {
-                        panic!("Syntax error in parsing Params struct. Expected Punctuation like , or =");
+            };
+            for nested in list.nested.iter() {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(p)) if p.is_ident(Self::SKIP) => {
+                        skip = true
+                    }
+                    NestedMeta::Meta(Meta::Path(p))
+                        if p.is_ident(Self::SENSITIVE) =>
+                    {
+                        sensitive = true
                     }
-                    let literal = literal.to_string();
-                    match current_ident {
-                        Some(i) => {
-                            match &i.to_string()[..] {
-                                Self::TABLE_NAME => table_name = Some(literal.replace('\"', "")),
-                                Self::PRIMARY_KEY => primary_key = Some(literal.replace('\"', "")),
-                                _ => panic!("Unknown parameter '{}' given!", i),
+                    NestedMeta::Meta(Meta::Path(p))
+                        if p.is_ident(Self::SNAPSHOT) =>
+                    {
+                        snapshot = true
+                    }
+                    NestedMeta::Meta(Meta::NameValue(nv))
+                        if nv.path.is_ident(Self::COLUMN) =>
+                    {
+                        match &nv.lit {
+                            Lit::Str(s) => column = Some(s.parse()?),
+                            lit => {
+                                return Err(Error::new(
+                                    lit.span(),
+                                    "Expected a string literal",
+                                ))
                             }
                         }
-                        None => panic!("Syntax error in parsing Params struct. Identifier expected."),
                     }
-                    current_ident = None;
-                    parse_state = ParseState::Punct;
+                    _ => {
+                        return Err(Error::new(
+                            nested.span(),
+                            format!(
+                                "Unknown parameter given! Possible values are: {} = \"...\", {}, {}, {}",
+                                Self::COLUMN,
+                                Self::SKIP,
+                                Self::SENSITIVE,
+                                Self::SNAPSHOT,
+                            ),
+                        ))
+                    }
                 }
-                _ => continue,
             }
         }
 
-        let table_name = if let Some(t) = table_name {
-            t
-        } else {
-            panic!("Missing parameter table_name. Please add it to the model attribute, e.g. table_name = \"my_new_table\"!");
-        };
+        Ok(Field {
+            column: column.unwrap_or_else(|| ident.clone()),
+            ident,
+            ty: field.ty.clone(),
+            skip: skip || snapshot,
+            sensitive,
+            snapshot,
+        })
+    }
+}
+
+/// A parent of the model given by the `#[belongs_to(...)]` attribute.
+pub struct Association {
+    /// The type of the parent model.
+    pub parent: ::syn::TypePath,
+    /// The column that references the primary key of the parent, defaults to
+    /// the snake cased name of the parent followed by `_id`.
+    pub foreign_key: ::syn::Ident,
+    /// The location of the attribute, used for error messages.
+    span: Span,
+}
+
+impl Association {
+    const ATTRIBUTE: &'static str = "belongs_to";
+    const FOREIGN_KEY: &'static str = "foreign_key";
+
+    /// Collects all `#[belongs_to(...)]` attributes of the given `struct`.
+    pub fn collect(ast: &::syn::DeriveInput) -> Result<Vec<Self>> {
+        ast.attrs
+            .iter()
+            .filter(|a| a.path.is_ident(Self::ATTRIBUTE))
+            .map(Self::parse)
+            .collect()
+    }
+
+    /// Removes all `#[belongs_to(...)]` attributes from the given `struct`, as
+    /// they are not known to the compiler.
+    pub fn strip_attributes(ast: &mut ::syn::DeriveInput) {
+        ast.attrs.retain(|a| !a.path.is_ident(Self::ATTRIBUTE));
+    }
+
+    /// Makes sure that the foreign key is a column of the model.
+    pub fn validate(&self, fields: &[Field]) -> Result<()> {
+        match fields.iter().find(|f| f.column == self.foreign_key) {
+            Some(f) if f.skip => Err(Error::new(
+                self.span,
+                format!("The foreign key '{}' must not be skipped!", f.ident),
+            )),
+            Some(_) => Ok(()),
+            None => Err(Error::new(
+                self.span,
+                format!(
+                    "The foreign key '{}' is not a column of the model!",
+                    self.foreign_key
+                ),
+            )),
+        }
+    }
+
+    /// Returns the member that stores the foreign key.
+    pub fn foreign_key_field<'a>(&self, fields: &'a [Field]) -> &'a Field {
+        fields
+            .iter()
+            .find(|f| f.column == self.foreign_key)
+            .expect("The foreign key has been validated before")
+    }
 
-        let primary_key = if let Some(k) = primary_key {
-            k
-        } else {
-            panic!("Missing parameter primary_key. Please add it to the model attribute, e.g. primary_key = \"id\"!");
+    /// Parses a `#[belongs_to(Parent, foreign_key = "parent_id")]` attribute.
+    fn parse(attr: &::syn::Attribute) -> Result<Self> {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => {
+                return Err(Error::new(
+                    meta.span(),
+                    "Expected e.g. #[belongs_to(User)]",
+                ))
+            }
+        };
+        let mut nested = list.nested.iter();
+        let parent = match nested.next() {
+            Some(NestedMeta::Meta(Meta::Path(path))) => ::syn::TypePath {
+                qself: None,
+                path: path.clone(),
+            },
+            _ => {
+                return Err(Error::new(
+                    list.span(),
+                    "Expected the parent as first parameter, e.g. #[belongs_to(User)]",
+                ))
+            }
         };
+        let mut foreign_key = None;
+        for meta in nested {
+            match meta {
+                NestedMeta::Meta(Meta::NameValue(nv))
+                    if nv.path.is_ident(Self::FOREIGN_KEY) =>
+                {
+                    match &nv.lit {
+                        Lit::Str(s) => foreign_key = Some(s.parse()?),
+                        lit => {
+                            return Err(Error::new(
+                                lit.span(),
+                                "Expected a string literal",
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    return Err(Error::new(
+                        meta.span(),
+                        format!(
+                            "Unknown parameter given! Possible values are: {} = \"...\"",
+                            Self::FOREIGN_KEY,
+                        ),
+                    ))
+                }
+            }
+        }
+        let foreign_key = foreign_key.unwrap_or_else(|| {
+            let name = &parent.path.segments.last().unwrap().ident;
+            ::syn::Ident::new(
+                &format!("{}_id", to_snake_case(&name.to_string())),
+                Span::call_site(),
+            )
+        });
 
-        Params {
-            table_name,
-            primary_key,
+        Ok(Association {
+            parent,
+            foreign_key,
+            span: list.span(),
+        })
+    }
+}
+
+/// Converts the given `CamelCase` name to `snake_case`.
+fn to_snake_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            result.push('_');
         }
+        result.extend(c.to_lowercase());
     }
+    result
 }
@@ -0,0 +1,204 @@
+use {
+    crate::params::{Field, Params},
+    quote::quote,
+    syn::{parse_quote, DeriveInput},
+};
+
+/// Returns the attributes deriving `Serialize` and `Deserialize` for models
+/// marked by `serde`.
+pub(crate) fn derive(params: &Params) -> ::proc_macro2::TokenStream {
+    if !params.serde {
+        return quote! {};
+    }
+    quote! {
+        #[derive(::naphtha::serde::Serialize, ::naphtha::serde::Deserialize)]
+        #[serde(crate = "::naphtha::serde")]
+    }
+}
+
+/// Adds the `#[serde(...)]` attributes to the members of models marked by
+/// `serde`. Members are named by their column, skipped and snapshot members
+/// are left out.
+pub(crate) fn add_attributes(
+    ast: &mut DeriveInput,
+    params: &Params,
+    fields: &[Field],
+) {
+    if !params.serde {
+        return;
+    }
+    let data = match &mut ast.data {
+        ::syn::Data::Struct(data) => data,
+        _ => return,
+    };
+    for (member, field) in data.fields.iter_mut().zip(fields) {
+        if field.skip {
+            member.attrs.push(parse_quote!(#[serde(skip)]));
+            continue;
+        }
+        if field.column != field.ident {
+            let column = field.column.to_string();
+            member.attrs.push(parse_quote!(#[serde(rename = #column)]));
+        }
+    }
+}
+
+/// Implements `naphtha::json::JsonTransfer` of models marked by `serde` for
+/// all enabled backends.
+pub(crate) fn impl_json_transfer(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+) -> ::proc_macro2::TokenStream {
+    if !params.serde {
+        return quote! {};
+    }
+    #[cfg(not(any(feature = "sqlite", feature = "mysql", feature = "pg")))]
+    let impls = quote! {};
+    #[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+    let impls = crate::database_impl::connections()
+        .iter()
+        .map(|connection| impl_connection(ast, params, fields, connection))
+        .collect();
+    impls
+}
+
+/// Implements `naphtha::json::JsonTransfer` for the given connection.
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+fn impl_connection(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+    connection: &crate::database_impl::Connection,
+) -> ::proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let load = |body| (connection.dispatch)(quote! { c }, body);
+    let table = params.table_path();
+
+    let mut bounds = vec![parse_quote! {
+        Self: ::naphtha::serde::Serialize + ::naphtha::serde::de::DeserializeOwned
+    }];
+    for backend in connection.backends.iter() {
+        bounds.extend(crate::generics::field_bounds(
+            &ast.generics,
+            params,
+            fields,
+            backend,
+        ));
+    }
+    let generics = crate::generics::extend(&ast.generics, vec![], bounds);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    let select = crate::instrument::run_statement(
+        ast,
+        quote! {
+            #table::table
+                .order(#table::table.primary_key())
+                .limit(::naphtha::json::BATCH_SIZE)
+                .offset(count as i64)
+        },
+        quote! { query.load::<Self>(&*c) },
+    );
+    let export = load(quote! {
+        c.transaction::<_, ::naphtha::diesel::result::Error, _>(|| {
+            let mut count = 0;
+            loop {
+                let batch = #select?;
+                for model in batch.iter() {
+                    writer.write(model)?;
+                }
+                count += batch.len();
+                if (batch.len() as i64) < ::naphtha::json::BATCH_SIZE {
+                    writer.flush()?;
+                    return Ok(count);
+                }
+            }
+        })
+    });
+
+    // the primary key is inserted as given
+    let values = fields.iter().filter(|f| !f.skip).map(|f| {
+        let ident = &f.ident;
+        let column = params.column_path(f);
+        quote! { #column.eq(&model.#ident), }
+    });
+    // diesel does not insert multiple rows by one statement on SQLite
+    let insert = crate::instrument::run_statement(
+        ast,
+        quote! {
+            ::naphtha::diesel::insert_into(#table::table).values((#(#values)*))
+        },
+        quote! { query.execute(&*c) },
+    );
+    // sequences are advanced past the imported keys on PostgreSQL
+    let advance_sequence = fields
+        .iter()
+        .find(|f| {
+            params.key_strategy == crate::params::KeyStrategy::Database
+                && params.is_primary_key(&f.ident)
+        })
+        .map(|f| {
+            let table_name = &params.table_name;
+            let column = f.column.to_string();
+            quote! {
+                ::naphtha::json::AdvanceSequence::advance_sequence(
+                    &*c,
+                    #table_name,
+                    #column,
+                )?;
+            }
+        });
+    let import = load(quote! {
+        c.transaction::<_, ::naphtha::diesel::result::Error, _>(|| {
+            let mut count = 0;
+            loop {
+                let batch = reader.read_batch::<Self>()?;
+                if batch.is_empty() {
+                    #advance_sequence
+                    return Ok(count);
+                }
+                for model in batch.iter() {
+                    #insert?;
+                }
+                count += batch.len();
+            }
+        })
+    });
+
+    let enter_export =
+        crate::instrument::enter_operation("export_json", ast, params);
+    let enter_import =
+        crate::instrument::enter_operation("import_json", ast, params);
+    let record_result = crate::instrument::map_record_result();
+    let connection_type = &connection.ty;
+
+    quote! {
+        impl #impl_generics ::naphtha::json::JsonTransfer<#connection_type> for #name #ty_generics
+        #where_clause
+        {
+            #[track_caller]
+            fn export_json<W: ::std::io::Write>(
+                conn: &::naphtha::DatabaseConnection<#connection_type>,
+                writer: W,
+            ) -> Result<usize, ::naphtha::ConnectionError> {
+                use ::naphtha::diesel::{Connection, QueryDsl, RunQueryDsl, Table};
+                #enter_export
+                let writer = ::naphtha::json::LineWriter::new(writer);
+                // not retried, the output has been written partially
+                conn.write::<usize, _>(|c| #export)#record_result
+            }
+
+            #[track_caller]
+            fn import_json<R: ::std::io::BufRead>(
+                conn: &::naphtha::DatabaseConnection<#connection_type>,
+                reader: R,
+            ) -> Result<usize, ::naphtha::ConnectionError> {
+                use ::naphtha::diesel::{Connection, ExpressionMethods, RunQueryDsl};
+                #enter_import
+                let reader = ::naphtha::json::LineReader::new(reader);
+                conn.write::<usize, _>(|c| #import)#record_result
+            }
+        }
+    }
+}
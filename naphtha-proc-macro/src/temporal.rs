@@ -0,0 +1,528 @@
+use {
+    crate::params::{Field, Params},
+    quote::quote,
+    syn::DeriveInput,
+};
+
+/// Executes the given update or delete statement within a transaction after
+/// copying the current row of the model `self` to the history table.
+pub(crate) fn archive(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+    statement: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if !params.temporal {
+        return statement;
+    }
+    let values = params
+        .primary_key_fields(fields)
+        .into_iter()
+        .map(|f| {
+            let ident = &f.ident;
+            quote! { &self.#ident }
+        })
+        .collect::<Vec<_>>();
+    let history_condition =
+        key_condition(params, fields, quote! { __naphtha_history }, &values);
+    // the version is valid since the previous one got replaced
+    let copy = copy_version(
+        ast,
+        params,
+        fields,
+        &values,
+        quote! {
+            .sql(&format!(
+                "(SELECT MAX({history}.valid_to) FROM {history}",
+                history = __naphtha_history,
+            ))
+            #history_condition
+            .sql(")")
+        },
+    );
+    quote! {
+        ::naphtha::diesel::Connection::transaction::<_, ::naphtha::diesel::result::Error, _>(&*c, || {
+            let __naphtha_now = ::naphtha::temporal::now();
+            #copy?;
+            #statement
+        })
+    }
+}
+
+/// Records the insertion after the given query, that returns the primary key
+/// of the inserted model, by copying the inserted row to the history table
+/// with the insertion time as `valid_from` and `valid_to`. It is executed
+/// within the insert transaction.
+pub(crate) fn archive_insert(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+    insert_query: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if !params.temporal {
+        return insert_query;
+    }
+    let values = if params.is_composite_key() {
+        (0..params.primary_key.len())
+            .map(|i| {
+                let index = ::syn::Index::from(i);
+                quote! { &key.#index }
+            })
+            .collect::<Vec<_>>()
+    } else {
+        vec![quote! { &key }]
+    };
+    let copy = copy_version(
+        ast,
+        params,
+        fields,
+        &values,
+        quote! {
+            .bind::<::naphtha::diesel::sql_types::Timestamp, _>(__naphtha_now)
+        },
+    );
+    quote! {
+        let key: ::naphtha::diesel::result::QueryResult<<Self as ::naphtha::DatabaseModel>::PrimaryKey> = {
+            #insert_query
+        };
+        let key = key?;
+        let __naphtha_now = ::naphtha::temporal::now();
+        #copy?;
+        Ok(key)
+    }
+}
+
+/// Copies the row of the model with the primary key `values` to the history
+/// table, valid from the value selected by the given SQL until
+/// `__naphtha_now`. The history table is quoted as `__naphtha_history`.
+fn copy_version(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+    values: &[::proc_macro2::TokenStream],
+    valid_from: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    let quote_identifiers = [
+        quote_table(params),
+        quote_history(params),
+        quote_columns(fields),
+        quote_keys(params, fields),
+    ];
+    let table_condition =
+        key_condition(params, fields, quote! { __naphtha_table }, values);
+    let copy = crate::instrument::run_statement(
+        ast,
+        quote! {
+            ::naphtha::diesel::dsl::sql::<::naphtha::diesel::sql_types::Integer>(&format!(
+                "INSERT INTO {history} ({columns}, valid_from, valid_to) SELECT {columns}, ",
+                history = __naphtha_history,
+                columns = __naphtha_columns,
+            ))
+                #valid_from
+                .sql(", ")
+                .bind::<::naphtha::diesel::sql_types::Timestamp, _>(__naphtha_now)
+                .sql(&format!(" FROM {}", __naphtha_table))
+                #table_condition
+        },
+        quote! { ::naphtha::diesel::RunQueryDsl::execute(query, &*c) },
+    );
+    quote! {{
+        #(#quote_identifiers)*
+        #copy
+    }}
+}
+
+/// Returns the declarations of the `as_of` query and `history` of a
+/// `temporal` model for the query trait.
+pub(crate) fn trait_queries(
+    params: &Params,
+    fields: &[Field],
+) -> ::proc_macro2::TokenStream {
+    if !params.temporal {
+        return quote! {};
+    }
+    let key_type = key_type(params, fields);
+    let as_of = as_of_name(params, fields);
+    quote! {
+            /// Queries the database for the version of the model with the given
+            /// primary key that was valid at the given UTC time.
+            fn #as_of(conn: &::naphtha::DatabaseConnection<DB>, primary_key: &#key_type, timestamp: ::naphtha::chrono::NaiveDateTime)
+                -> Result<Self, Self::Error>;
+            /// Queries all previous versions of the model with the given primary
+            /// key, the oldest first.
+            fn history(conn: &::naphtha::DatabaseConnection<DB>, primary_key: &#key_type)
+                -> Result<Vec<::naphtha::temporal::Version<Self>>, Self::Error>;
+    }
+}
+
+/// Implements the `as_of` query and `history` of a `temporal` model on the
+/// given connection type. `load` receives the body of the query and returns
+/// it adapted to the locked connection `c`.
+pub(crate) fn impl_queries(
+    ast: &DeriveInput,
+    params: &Params,
+    fields: &[Field],
+    connection: ::proc_macro2::TokenStream,
+    load: impl Fn(::proc_macro2::TokenStream) -> ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if !params.temporal {
+        return quote! {};
+    }
+    let sql_types = fields.iter().filter(|f| !f.skip).map(|f| {
+        let column = params.column_path(f);
+        quote! { <#column as ::naphtha::diesel::Expression>::SqlType }
+    });
+    let sql_type = quote! { (#(#sql_types,)*) };
+    let values = if params.is_composite_key() {
+        (0..params.primary_key.len())
+            .map(|i| {
+                let index = ::syn::Index::from(i);
+                quote! { &primary_key.#index }
+            })
+            .collect::<Vec<_>>()
+    } else {
+        vec![quote! { primary_key }]
+    };
+    let history_condition =
+        key_condition(params, fields, quote! { __naphtha_history }, &values);
+    let table_condition =
+        key_condition(params, fields, quote! { __naphtha_table }, &values);
+    let key_type = key_type(params, fields);
+    let as_of = as_of_name(params, fields);
+    let quote_table = quote_table(params);
+    let quote_history = quote_history(params);
+    let quote_columns = quote_columns(fields);
+    let quote_keys = quote_keys(params, fields);
+
+    let archived = crate::instrument::run_statement(
+        ast,
+        quote! {
+            ::naphtha::diesel::dsl::sql::<#sql_type>(&format!(
+                "SELECT {} FROM {}",
+                __naphtha_columns,
+                __naphtha_history,
+            ))
+                #history_condition
+                .sql(&format!(
+                    " AND ({history}.valid_from IS NULL OR {history}.valid_from <= ",
+                    history = __naphtha_history,
+                ))
+                .bind::<::naphtha::diesel::sql_types::Timestamp, _>(timestamp)
+                .sql(&format!(") AND {}.valid_to > ", __naphtha_history))
+                .bind::<::naphtha::diesel::sql_types::Timestamp, _>(timestamp)
+                .sql(&format!(
+                    " ORDER BY {}.history_id DESC LIMIT 1",
+                    __naphtha_history,
+                ))
+        },
+        quote! {
+            ::naphtha::diesel::OptionalExtension::optional(query.get_result::<Self>(&*c))
+        },
+    );
+    // the current row is valid since the last archived version, or since
+    // its insertion
+    let current = crate::instrument::run_statement(
+        ast,
+        quote! {
+            ::naphtha::diesel::dsl::sql::<#sql_type>(&format!(
+                "SELECT {} FROM {}",
+                __naphtha_columns,
+                __naphtha_table,
+            ))
+                #table_condition
+                .sql(&format!(" AND NOT EXISTS (SELECT 1 FROM {}", __naphtha_history))
+                #history_condition
+                .sql(&format!(" AND {}.valid_to > ", __naphtha_history))
+                .bind::<::naphtha::diesel::sql_types::Timestamp, _>(timestamp)
+                .sql(")")
+        },
+        quote! { query.get_result::<Self>(&*c) },
+    );
+    let load_as_of = load(quote! {
+        #quote_table
+        #quote_history
+        #quote_columns
+        #quote_keys
+        match #archived? {
+            Some(model) => Ok(model),
+            None => #current,
+        }
+    });
+
+    // the versions recording the insertion have never been valid
+    let versions = crate::instrument::run_statement(
+        ast,
+        quote! {
+            ::naphtha::diesel::dsl::sql::<(
+                #sql_type,
+                ::naphtha::diesel::sql_types::Nullable<::naphtha::diesel::sql_types::Timestamp>,
+                ::naphtha::diesel::sql_types::Timestamp,
+            )>(&format!(
+                "SELECT {columns}, {history}.valid_from, {history}.valid_to FROM {history}",
+                columns = __naphtha_columns,
+                history = __naphtha_history,
+            ))
+                #history_condition
+                .sql(&format!(
+                    " AND ({history}.valid_from IS NULL OR {history}.valid_from < {history}.valid_to) ORDER BY {history}.history_id",
+                    history = __naphtha_history,
+                ))
+        },
+        quote! {
+            query.load::<(
+                Self,
+                Option<::naphtha::chrono::NaiveDateTime>,
+                ::naphtha::chrono::NaiveDateTime,
+            )>(&*c)
+        },
+    );
+    let load_history = load(quote! {
+        #quote_history
+        #quote_columns
+        #quote_keys
+        #versions
+    });
+
+    let enter_as_of =
+        crate::instrument::enter_operation(&as_of.to_string(), ast, params);
+    let enter_history =
+        crate::instrument::enter_operation("history", ast, params);
+    let record_primary_key =
+        crate::instrument::record_primary_key(quote! { primary_key });
+    let record_result = crate::instrument::map_record_result();
+
+    quote! {
+            /// Queries the database for the version of the model with the given
+            /// primary key that was valid at the given UTC time.
+            #[track_caller]
+            fn #as_of(conn: &::naphtha::DatabaseConnection<#connection>, primary_key: &#key_type, timestamp: ::naphtha::chrono::NaiveDateTime)
+                -> Result<Self, ::naphtha::ConnectionError> {
+                use ::naphtha::diesel::RunQueryDsl;
+                #enter_as_of
+                #record_primary_key
+                conn.read::<Self, _>(|c| {
+                    #load_as_of
+                })#record_result
+            }
+
+            /// Queries all previous versions of the model with the given primary
+            /// key, the oldest first.
+            #[track_caller]
+            fn history(conn: &::naphtha::DatabaseConnection<#connection>, primary_key: &#key_type)
+                -> Result<Vec<::naphtha::temporal::Version<Self>>, ::naphtha::ConnectionError> {
+                use ::naphtha::diesel::RunQueryDsl;
+                #enter_history
+                #record_primary_key
+                conn.read::<Vec<::naphtha::temporal::Version<Self>>, _>(|c| {
+                    let versions = { #load_history }?;
+                    Ok(versions
+                        .into_iter()
+                        .map(|(model, valid_from, valid_to)| ::naphtha::temporal::Version {
+                            model,
+                            valid_from,
+                            valid_to,
+                        })
+                        .collect())
+                })#record_result
+            }
+    }
+}
+
+/// Returns the names of the persisted columns of the model.
+pub(crate) fn column_names(fields: &[Field]) -> Vec<String> {
+    fields
+        .iter()
+        .filter(|f| !f.skip)
+        .map(|f| f.column.to_string())
+        .collect()
+}
+
+/// Quotes the given identifiers for the backend of the locked connection `c`
+/// as the variable `name`, joined by commas.
+fn quote_identifiers(
+    name: &str,
+    identifiers: &[String],
+) -> ::proc_macro2::TokenStream {
+    let name = ::syn::Ident::new(name, ::proc_macro2::Span::call_site());
+    quote! {
+        let #name = ::naphtha::temporal::quote_identifiers(&*c, &[#(#identifiers),*])?;
+    }
+}
+
+/// Quotes the model table as `__naphtha_table`.
+fn quote_table(params: &Params) -> ::proc_macro2::TokenStream {
+    quote_identifiers(
+        "__naphtha_table",
+        std::slice::from_ref(&params.table_name),
+    )
+}
+
+/// Quotes the history table as `__naphtha_history`.
+fn quote_history(params: &Params) -> ::proc_macro2::TokenStream {
+    quote_identifiers("__naphtha_history", &[params.history_table_name()])
+}
+
+/// Quotes the persisted columns as `__naphtha_columns`.
+fn quote_columns(fields: &[Field]) -> ::proc_macro2::TokenStream {
+    quote_identifiers("__naphtha_columns", &column_names(fields))
+}
+
+/// Quotes the primary key columns as array `__naphtha_keys`.
+fn quote_keys(params: &Params, fields: &[Field]) -> ::proc_macro2::TokenStream {
+    let keys = params.primary_key_fields(fields).into_iter().map(|f| {
+        let column = f.column.to_string();
+        quote! { ::naphtha::temporal::quote_identifiers(&*c, &[#column])? }
+    });
+    quote! { let __naphtha_keys = [#(#keys),*]; }
+}
+
+/// Returns the `WHERE` clause matching the primary key columns of the table,
+/// quoted as the given variable, with the given values, appended to a raw
+/// SQL query. The key columns are quoted as `__naphtha_keys`.
+fn key_condition(
+    params: &Params,
+    fields: &[Field],
+    table: ::proc_macro2::TokenStream,
+    values: &[::proc_macro2::TokenStream],
+) -> ::proc_macro2::TokenStream {
+    params
+        .primary_key_fields(fields)
+        .into_iter()
+        .zip(values)
+        .enumerate()
+        .map(|(i, (field, value))| {
+            let column = params.column_path(field);
+            let sql = format!(
+                " {} {{}}.{{}} = ",
+                if i == 0 { "WHERE" } else { "AND" },
+            );
+            quote! {
+                .sql(&format!(#sql, #table, __naphtha_keys[#i]))
+                .bind::<<#column as ::naphtha::diesel::Expression>::SqlType, _>(#value)
+            }
+        })
+        .collect()
+}
+
+/// Returns the type of the primary key, a tuple for composite keys.
+fn key_type(params: &Params, fields: &[Field]) -> ::proc_macro2::TokenStream {
+    let key_fields = params.primary_key_fields(fields);
+    if params.is_composite_key() {
+        let key_types = key_fields.iter().map(|f| &f.ty);
+        quote! { (#(#key_types,)*) }
+    } else {
+        let key_type = &key_fields[0].ty;
+        quote! { #key_type }
+    }
+}
+
+/// Returns the name of the `as_of` query, e.g. `query_by_id_as_of`, or
+/// `query_by_primary_key_as_of` for composite keys.
+fn as_of_name(params: &Params, fields: &[Field]) -> ::syn::Ident {
+    let name = if params.is_composite_key() {
+        "query_by_primary_key_as_of".to_string()
+    } else {
+        let field = params.primary_key_fields(fields)[0];
+        format!("query_by_{}_as_of", field.ident).to_lowercase()
+    };
+    ::syn::Ident::new(&name, ::proc_macro2::Span::call_site())
+}
+
+/// Returns the statements creating the history table of a `temporal` model
+/// as `h`, the SQL of the given barrel backend. The column types are taken
+/// from the given constant of `naphtha::temporal::HistoryColumnType`.
+#[cfg(any(
+    feature = "barrel-sqlite",
+    feature = "barrel-mysql",
+    feature = "barrel-pg"
+))]
+pub(crate) fn history_migration_up(
+    params: &Params,
+    fields: &[Field],
+    backend: ::proc_macro2::TokenStream,
+    column_type: &str,
+) -> ::proc_macro2::TokenStream {
+    if !params.temporal {
+        return quote! {};
+    }
+    let column_type =
+        ::syn::Ident::new(column_type, ::proc_macro2::Span::call_site());
+    let history = params.history_table_name();
+    let columns = fields.iter().filter(|f| !f.skip).map(|f| {
+        let name = f.column.to_string();
+        let sql_type = {
+            let column = params.column_path(f);
+            quote! {
+                <<#column as ::naphtha::diesel::Expression>::SqlType as ::naphtha::temporal::HistoryColumnType>
+            }
+        };
+        quote! {
+            t.add_column(
+                #name,
+                ::naphtha::barrel::types::custom(#sql_type::#column_type)
+                    .nullable(#sql_type::NULLABLE),
+            );
+        }
+    });
+    let timestamp = quote! {
+        <::naphtha::diesel::sql_types::Timestamp as ::naphtha::temporal::HistoryColumnType>::#column_type
+    };
+    quote! {
+        let h = {
+            let mut h = ::naphtha::barrel::Migration::new();
+            h.create_table_if_not_exists(#history, |t| {
+                t.add_column("history_id", ::naphtha::barrel::types::primary());
+                #(#columns)*
+                t.add_column(
+                    "valid_from",
+                    ::naphtha::barrel::types::custom(#timestamp).nullable(true),
+                );
+                t.add_column("valid_to", ::naphtha::barrel::types::custom(#timestamp));
+            });
+            h.make::<#backend>()
+        };
+    }
+}
+
+/// Returns the statements dropping the history table of a `temporal` model
+/// as `h`, the SQL of the given barrel backend.
+#[cfg(any(
+    feature = "barrel-sqlite",
+    feature = "barrel-mysql",
+    feature = "barrel-pg"
+))]
+pub(crate) fn history_migration_down(
+    params: &Params,
+    backend: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if !params.temporal {
+        return quote! {};
+    }
+    let history = params.history_table_name();
+    quote! {
+        let h = {
+            let mut h = ::naphtha::barrel::Migration::new();
+            h.drop_table_if_exists(#history);
+            h.make::<#backend>()
+        };
+    }
+}
+
+/// Executes the history migration `h` after the given migration `query` on
+/// the connection `c`, returning the result of the latter.
+#[cfg(any(
+    feature = "barrel-sqlite",
+    feature = "barrel-mysql",
+    feature = "barrel-pg"
+))]
+pub(crate) fn execute_history_migration(
+    params: &Params,
+    query: ::proc_macro2::TokenStream,
+) -> ::proc_macro2::TokenStream {
+    if !params.temporal {
+        return query;
+    }
+    quote! {
+        #query.and_then(|u| ::naphtha::diesel::sql_query(h).execute(&*c).map(|_| u))
+    }
+}
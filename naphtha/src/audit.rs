@@ -0,0 +1,425 @@
+//! Audit log of the changes to models marked by `#[model(audited)]`.
+//!
+//! Every [insert](crate::DatabaseModelModifier::insert),
+//! [update](crate::DatabaseModelModifier::update) and
+//! [remove](crate::DatabaseModelModifier::remove) of an audited model writes
+//! an entry to the shared `naphtha_audit` table within the same transaction.
+//! The entry contains the table, the primary key, the operation, the changed
+//! columns with their values before and after as JSON, a timestamp and the
+//! actor set by [set_actor](crate::DatabaseConnection::set_actor).
+//!
+//! The entries of a model are queried by [Audited::audit_history].
+//!
+//! # The audit table
+//!
+//! The `naphtha_audit` table is not part of the schema of your models. It
+//! has to be created by [create_table] before the first change of an audited
+//! model, otherwise the change fails as its entry cannot be written. The
+//! migrations generated with one of the `barrel-*` features create it as
+//! well when migrating an audited model up, but do not drop it, as it is
+//! shared by all models.
+//!
+//! ```ignore
+//! #[model(table_name = "persons", primary_key = "id", audited)]
+//! pub struct Person {
+//!     id: i32,
+//!     pub name: String,
+//!     #[naphtha(sensitive)]
+//!     pub password_hash: String,
+//! }
+//!
+//! naphtha::audit::create_table(&db)?;
+//! db.set_actor(Some("admin"));
+//! person.update(&db)?;
+//! for entry in Person::audit_history(&db, &person.id)? {
+//!     println!("{} {} {}", entry.operation, entry.changes, entry.created_at);
+//! }
+//! ```
+//!
+//! The columns of the model are required to implement
+//! [Serialize], members marked with
+//! `#[naphtha(sensitive)]` are recorded as `"<redacted>"`.
+
+use {
+    crate::{
+        serde::Serialize,
+        serde_json::{self, Map, Value},
+        ConnectionError,
+        DatabaseConnection,
+        DatabaseModel,
+    },
+    chrono::NaiveDateTime,
+    diesel::{
+        backend::Backend,
+        deserialize::Queryable,
+        result::{Error, QueryResult},
+        sql_types::{Integer, Nullable, Text, Timestamp},
+    },
+};
+
+/// The name of the table containing the audit log.
+pub const TABLE_NAME: &str = "naphtha_audit";
+
+/// The value recorded for members marked with `#[naphtha(sensitive)]`.
+pub const REDACTED: &str = "<redacted>";
+
+// diesel 1.4 implements the traits of the table within named constants,
+// which are reported as non-local although they belong to this module
+#[doc(hidden)]
+#[allow(non_local_definitions)]
+pub mod schema {
+    table! {
+        naphtha_audit (id) {
+            id -> Integer,
+            table_name -> Text,
+            primary_key -> Text,
+            operation -> Text,
+            changes -> Text,
+            actor -> Nullable<Text>,
+            created_at -> Timestamp,
+        }
+    }
+}
+
+/// An entry of the audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// The id of the entry, ascending in the order of the changes.
+    pub id: i32,
+    /// The table of the changed model.
+    pub table_name: String,
+    /// The primary key of the changed model as JSON.
+    pub primary_key: String,
+    /// The operation, one of `insert`, `update` and `remove`.
+    pub operation: String,
+    /// The changed columns as JSON object, containing the value `before` and
+    /// `after` the change for every column, e.g.
+    /// `{"name":{"before":"Jane","after":"John"}}`. The value before an
+    /// insert and after a remove is `null`.
+    pub changes: String,
+    /// The actor that has been set on the connection during the change.
+    pub actor: Option<String>,
+    /// The time of the change in UTC.
+    pub created_at: NaiveDateTime,
+}
+
+impl AuditEntry {
+    /// Parses the [changes](AuditEntry::changes) of the entry.
+    pub fn changes_json(&self) -> serde_json::Result<Value> {
+        serde_json::from_str(&self.changes)
+    }
+}
+
+type AuditRow = (
+    i32,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    NaiveDateTime,
+);
+type AuditSqlType =
+    (Integer, Text, Text, Text, Text, Nullable<Text>, Timestamp);
+
+impl<DB: Backend> Queryable<AuditSqlType, DB> for AuditEntry
+where
+    AuditRow: Queryable<AuditSqlType, DB>,
+{
+    type Row = <AuditRow as Queryable<AuditSqlType, DB>>::Row;
+
+    fn build(row: Self::Row) -> Self {
+        let (
+            id,
+            table_name,
+            primary_key,
+            operation,
+            changes,
+            actor,
+            created_at,
+        ) = AuditRow::build(row);
+        Self {
+            id,
+            table_name,
+            primary_key,
+            operation,
+            changes,
+            actor,
+            created_at,
+        }
+    }
+}
+
+/// A model whose changes are recorded in the audit log, implemented by
+/// `#[model(audited)]`.
+pub trait Audited: DatabaseModel + Sized {
+    /// The columns whose values are recorded as [REDACTED].
+    const SENSITIVE_COLUMNS: &'static [&'static str];
+
+    /// Returns the name and value of every persisted column except the
+    /// primary key.
+    fn audit_values(&self) -> serde_json::Result<Vec<(&'static str, Value)>>;
+
+    /// Returns the audit log entries of the model with the given primary key,
+    /// oldest first.
+    fn audit_history<T: AuditBackend>(
+        conn: &DatabaseConnection<T>,
+        primary_key: &Self::PrimaryKey,
+    ) -> Result<Vec<AuditEntry>, ConnectionError>
+    where
+        Self::PrimaryKey: Serialize,
+    {
+        let key = serde_json::to_string(primary_key).map_err(|e| {
+            ConnectionError::Query(Error::SerializationError(e.into()))
+        })?;
+        conn.read(|c| c.load_audit_entries(Self::table_name(), &key))
+    }
+}
+
+/// A new entry of the audit log.
+#[doc(hidden)]
+pub struct NewAuditEntry<'a> {
+    pub table_name: &'a str,
+    pub primary_key: String,
+    pub operation: &'a str,
+    pub changes: String,
+    pub actor: Option<&'a str>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Reads and writes the audit log on a connection.
+pub trait AuditBackend {
+    /// Creates the audit log table and its index if they do not exist.
+    fn create_audit_table(&self) -> QueryResult<()>;
+
+    /// Inserts the given entry into the audit log.
+    #[doc(hidden)]
+    fn insert_audit_entry(&self, entry: NewAuditEntry<'_>) -> QueryResult<()>;
+
+    /// Loads the entries of the given table and primary key, oldest first.
+    fn load_audit_entries(
+        &self,
+        table_name: &str,
+        primary_key: &str,
+    ) -> QueryResult<Vec<AuditEntry>>;
+}
+
+/// Implements [AuditBackend] for a diesel connection, creating the table by
+/// the given statements.
+macro_rules! impl_audit_backend {
+    ($connection:ty, $($statement:expr),+) => {
+        impl AuditBackend for $connection {
+            fn create_audit_table(&self) -> QueryResult<()> {
+                use diesel::connection::SimpleConnection;
+                $(self.batch_execute($statement)?;)+
+                Ok(())
+            }
+
+            fn insert_audit_entry(
+                &self,
+                entry: NewAuditEntry<'_>,
+            ) -> QueryResult<()> {
+                use {
+                    diesel::{ExpressionMethods, RunQueryDsl},
+                    schema::naphtha_audit::dsl::*,
+                };
+                diesel::insert_into(naphtha_audit)
+                    .values((
+                        table_name.eq(entry.table_name),
+                        primary_key.eq(entry.primary_key),
+                        operation.eq(entry.operation),
+                        changes.eq(entry.changes),
+                        actor.eq(entry.actor),
+                        created_at.eq(entry.created_at),
+                    ))
+                    .execute(self)
+                    .map(|_| ())
+            }
+
+            fn load_audit_entries(
+                &self,
+                table: &str,
+                key: &str,
+            ) -> QueryResult<Vec<AuditEntry>> {
+                use {
+                    diesel::{ExpressionMethods, QueryDsl, RunQueryDsl},
+                    schema::naphtha_audit::dsl::*,
+                };
+                naphtha_audit
+                    .filter(table_name.eq(table))
+                    .filter(primary_key.eq(key))
+                    .order(id.asc())
+                    .load(self)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "sqlite")]
+impl_audit_backend!(
+    diesel::SqliteConnection,
+    "CREATE TABLE IF NOT EXISTS naphtha_audit (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        table_name TEXT NOT NULL,
+        primary_key TEXT NOT NULL,
+        operation TEXT NOT NULL,
+        changes TEXT NOT NULL,
+        actor TEXT,
+        created_at TIMESTAMP NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS naphtha_audit_record
+        ON naphtha_audit (table_name, primary_key)"
+);
+
+#[cfg(feature = "mysql")]
+impl_audit_backend!(
+    diesel::MysqlConnection,
+    "CREATE TABLE IF NOT EXISTS naphtha_audit (
+        id INTEGER PRIMARY KEY AUTO_INCREMENT,
+        table_name VARCHAR(255) NOT NULL,
+        primary_key VARCHAR(255) NOT NULL,
+        operation VARCHAR(16) NOT NULL,
+        changes TEXT NOT NULL,
+        actor TEXT,
+        created_at DATETIME(6) NOT NULL,
+        INDEX naphtha_audit_record (table_name, primary_key)
+    )"
+);
+
+#[cfg(feature = "pg")]
+impl_audit_backend!(
+    diesel::PgConnection,
+    "CREATE TABLE IF NOT EXISTS naphtha_audit (
+        id SERIAL PRIMARY KEY,
+        table_name TEXT NOT NULL,
+        primary_key TEXT NOT NULL,
+        operation TEXT NOT NULL,
+        changes TEXT NOT NULL,
+        actor TEXT,
+        created_at TIMESTAMP NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS naphtha_audit_record
+        ON naphtha_audit (table_name, primary_key)"
+);
+
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+impl AuditBackend for crate::AnyConnection {
+    fn create_audit_table(&self) -> QueryResult<()> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            crate::AnyConnection::Sqlite(c) => c.create_audit_table(),
+            #[cfg(feature = "mysql")]
+            crate::AnyConnection::Mysql(c) => c.create_audit_table(),
+            #[cfg(feature = "pg")]
+            crate::AnyConnection::Pg(c) => c.create_audit_table(),
+        }
+    }
+
+    fn insert_audit_entry(&self, entry: NewAuditEntry<'_>) -> QueryResult<()> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            crate::AnyConnection::Sqlite(c) => c.insert_audit_entry(entry),
+            #[cfg(feature = "mysql")]
+            crate::AnyConnection::Mysql(c) => c.insert_audit_entry(entry),
+            #[cfg(feature = "pg")]
+            crate::AnyConnection::Pg(c) => c.insert_audit_entry(entry),
+        }
+    }
+
+    fn load_audit_entries(
+        &self,
+        table_name: &str,
+        primary_key: &str,
+    ) -> QueryResult<Vec<AuditEntry>> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            crate::AnyConnection::Sqlite(c) => {
+                c.load_audit_entries(table_name, primary_key)
+            }
+            #[cfg(feature = "mysql")]
+            crate::AnyConnection::Mysql(c) => {
+                c.load_audit_entries(table_name, primary_key)
+            }
+            #[cfg(feature = "pg")]
+            crate::AnyConnection::Pg(c) => {
+                c.load_audit_entries(table_name, primary_key)
+            }
+        }
+    }
+}
+
+/// Creates the `naphtha_audit` table on the primary connection if it does
+/// not exist.
+pub fn create_table<T: AuditBackend>(
+    conn: &DatabaseConnection<T>,
+) -> Result<(), ConnectionError> {
+    conn.write(|c| c.create_audit_table())
+}
+
+/// Writes the entry for the given operation on the model to the audit log.
+/// The model before or after the operation is `None` for inserts and
+/// removes.
+#[doc(hidden)]
+pub fn record<M, C>(
+    c: &C,
+    operation: &str,
+    primary_key: &M::PrimaryKey,
+    before: Option<&M>,
+    after: Option<&M>,
+    actor: Option<&str>,
+) -> QueryResult<()>
+where
+    M: Audited,
+    M::PrimaryKey: Serialize,
+    C: AuditBackend,
+{
+    let serialization =
+        |e: serde_json::Error| Error::SerializationError(e.into());
+    let changes = diff(before, after).map_err(serialization)?;
+    c.insert_audit_entry(NewAuditEntry {
+        table_name: M::table_name(),
+        primary_key: serde_json::to_string(primary_key)
+            .map_err(serialization)?,
+        operation,
+        changes: Value::Object(changes).to_string(),
+        actor,
+        created_at: chrono::Utc::now().naive_utc(),
+    })
+}
+
+/// Returns the columns that differ between both versions of the model.
+fn diff<M: Audited>(
+    before: Option<&M>,
+    after: Option<&M>,
+) -> serde_json::Result<Map<String, Value>> {
+    let before = before.map(M::audit_values).transpose()?;
+    let after = after.map(M::audit_values).transpose()?;
+    let columns = before
+        .as_ref()
+        .or(after.as_ref())
+        .map_or(vec![], |v| v.iter().map(|(column, _)| *column).collect());
+    let value = |values: &Option<Vec<(&str, Value)>>, i: usize| {
+        values.as_ref().map_or(Value::Null, |v| v[i].1.clone())
+    };
+
+    let mut changes = Map::new();
+    for (i, column) in columns.into_iter().enumerate() {
+        let (old, new) = (value(&before, i), value(&after, i));
+        if old == new {
+            continue;
+        }
+        let redact = |v: Value| match v {
+            Value::Null => Value::Null,
+            _ => Value::String(REDACTED.to_string()),
+        };
+        let (old, new) = match M::SENSITIVE_COLUMNS.contains(&column) {
+            true => (redact(old), redact(new)),
+            false => (old, new),
+        };
+        let mut change = Map::new();
+        change.insert("before".to_string(), old);
+        change.insert("after".to_string(), new);
+        changes.insert(column.to_string(), Value::Object(change));
+    }
+    Ok(changes)
+}
@@ -0,0 +1,343 @@
+//! Row level change notifications on SQLite.
+//!
+//! [watch_table](crate::DatabaseConnection::watch_table) notifies about every
+//! row that is inserted, updated or deleted in the given table on the primary
+//! connection, including the writes by raw SQL in
+//! [custom](crate::DatabaseConnection::custom) or by the generated operations
+//! of models without `events`. The changes are identified by the table and
+//! the `rowid` of the row. The affected model is loaded by
+//! [RowChange::reload] and its primary key is taken from there. For tables
+//! having an `INTEGER PRIMARY KEY`, the `rowid` is the primary key, which is
+//! the only way to identify deleted rows.
+//!
+//! # Update hook
+//!
+//! The changes are captured by `sqlite3_update_hook`, which is installed on
+//! every connection established by
+//! [connect](crate::DatabaseConnect::connect) or
+//! [connect_config](crate::DatabaseConnect::connect_config), including the
+//! re-established ones. Therefore:
+//!
+//! * Connections passed in by `From<SqliteConnection>` cannot be watched, as
+//!   diesel does not expose their handle.
+//! * The watches survive re-establishing the connection.
+//! * Changes by other connections or processes are not reported.
+//! * A change is reported when its statement is executed, even if the
+//!   surrounding transaction is rolled back afterwards.
+//! * Like the hook, rows deleted by `DELETE` without `WHERE` clause, which
+//!   SQLite executes by truncating the table, or replaced by
+//!   `ON CONFLICT REPLACE` are not reported.
+//! * Tables created `WITHOUT ROWID` are rejected, as the hook does not report
+//!   their changes.
+//!
+//! ```ignore
+//! let changes = db.watch::<Person>()?;
+//! for change in changes {
+//!     match change.reload::<Person>(&db)? {
+//!         Some(person) => cache.insert(change.rowid, person),
+//!         None => cache.remove(&change.rowid),
+//!     };
+//! }
+//! ```
+
+use {
+    crate::{ConnectionError, DatabaseConnection},
+    diesel::{
+        connection::SimpleConnection,
+        result::Error,
+        Connection,
+        QueryResult,
+        SqliteConnection,
+    },
+    libsqlite3_sys as ffi,
+    std::{
+        cell::Cell,
+        collections::{BTreeMap, HashMap},
+        ffi::{c_char, c_int, c_void, CStr},
+        ptr,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            mpsc::{self, Receiver, Sender},
+            Arc,
+            Mutex,
+            OnceLock,
+            PoisonError,
+            RwLock,
+            Weak,
+        },
+    },
+};
+
+/// The kind of change of a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RowOperation {
+    /// The row has been inserted.
+    Insert,
+    /// The row has been updated.
+    Update,
+    /// The row has been deleted.
+    Delete,
+}
+
+impl RowOperation {
+    /// Returns the operation of the given action code of the update hook.
+    fn from_action(action: c_int) -> Option<Self> {
+        match action {
+            ffi::SQLITE_INSERT => Some(Self::Insert),
+            ffi::SQLITE_UPDATE => Some(Self::Update),
+            ffi::SQLITE_DELETE => Some(Self::Delete),
+            _ => None,
+        }
+    }
+}
+
+/// A changed row of a watched table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RowChange {
+    /// The name of the table.
+    pub table: String,
+    /// The kind of change.
+    pub operation: RowOperation,
+    /// The `rowid` of the changed row.
+    pub rowid: i64,
+}
+
+impl RowChange {
+    /// Loads the model stored in the changed row. Returns `None` if the row
+    /// has been deleted in the meantime or if the change is of a different
+    /// table than the one of the model.
+    #[track_caller]
+    pub fn reload<M: QueryByRowid>(
+        &self,
+        conn: &DatabaseConnection<SqliteConnection>,
+    ) -> Result<Option<M>, ConnectionError> {
+        if self.table != M::table_name() {
+            return Ok(None);
+        }
+        match M::query_by_rowid(conn, self.rowid) {
+            Ok(model) => Ok(Some(model)),
+            Err(ConnectionError::Query(diesel::result::Error::NotFound)) => {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Queries a model by the `rowid` of its row, implemented for all models
+/// when using the `sqlite` feature.
+pub trait QueryByRowid: crate::DatabaseModel + Sized {
+    /// Queries the model stored in the row with the given `rowid` from the
+    /// primary connection.
+    fn query_by_rowid(
+        conn: &DatabaseConnection<SqliteConnection>,
+        rowid: i64,
+    ) -> Result<Self, ConnectionError>;
+}
+
+/// The result of registering [capture_handle] as auto extension of SQLite.
+static AUTO_EXTENSION: OnceLock<c_int> = OnceLock::new();
+
+/// The watchers of all connections with an installed update hook, by the id
+/// passed to the hook.
+static HOOKED: RwLock<BTreeMap<usize, Weak<RowWatchers>>> =
+    RwLock::new(BTreeMap::new());
+
+thread_local! {
+    /// The handle of the connection opened by [RowWatchers::establish] on
+    /// this thread, `None` while no connection is established.
+    static OPENED: Cell<Option<*mut ffi::sqlite3>> = const { Cell::new(None) };
+}
+
+/// The senders of all watchers of a connection by the name of their table.
+pub(crate) struct RowWatchers {
+    id: usize,
+    hooked: AtomicBool,
+    senders: Mutex<HashMap<String, Vec<Sender<RowChange>>>>,
+}
+
+impl Default for RowWatchers {
+    fn default() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            hooked: AtomicBool::new(false),
+            senders: Mutex::default(),
+        }
+    }
+}
+
+impl Drop for RowWatchers {
+    fn drop(&mut self) {
+        HOOKED
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.id);
+    }
+}
+
+impl RowWatchers {
+    /// Establishes a connection to the given url and installs the update hook
+    /// reporting its changes to these watchers.
+    pub(crate) fn establish(
+        self: &Arc<Self>,
+        url: &str,
+    ) -> anyhow::Result<SqliteConnection> {
+        let registered = *AUTO_EXTENSION.get_or_init(|| {
+            let entry: unsafe extern "C" fn(
+                *mut ffi::sqlite3,
+                *mut *mut c_char,
+                *const c_void,
+            ) -> c_int = capture_handle;
+            // SAFETY: SQLite calls the auto extensions with the arguments of
+            // their actual signature, the type is erased by its API only
+            unsafe {
+                ffi::sqlite3_auto_extension(Some(std::mem::transmute::<
+                    unsafe extern "C" fn(
+                        *mut ffi::sqlite3,
+                        *mut *mut c_char,
+                        *const c_void,
+                    ) -> c_int,
+                    unsafe extern "C" fn(),
+                >(entry)))
+            }
+        });
+        if registered != ffi::SQLITE_OK {
+            anyhow::bail!(
+                "Could not register the auto extension of SQLite, error code {}!",
+                registered
+            );
+        }
+
+        OPENED.with(|opened| opened.set(Some(ptr::null_mut())));
+        let connection = SqliteConnection::establish(url);
+        let handle = OPENED.with(Cell::take).filter(|h| !h.is_null());
+        let connection = connection?;
+        let handle = handle.ok_or_else(|| {
+            anyhow::anyhow!("The handle of the SQLite connection is missing!")
+        })?;
+        HOOKED
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(self.id, Arc::downgrade(self));
+        // SAFETY: the handle belongs to the connection that is still open,
+        // the hook is dropped together with it
+        unsafe {
+            ffi::sqlite3_update_hook(
+                handle,
+                Some(update_hook),
+                self.id as *mut c_void,
+            );
+        }
+        self.hooked.store(true, Ordering::Relaxed);
+        Ok(connection)
+    }
+
+    /// Adds a watcher of the given table of the connection `c`. Connections
+    /// that have not been established by naphtha and tables without `rowid`
+    /// are rejected.
+    pub(crate) fn watch(
+        &self,
+        c: &SqliteConnection,
+        table: &str,
+    ) -> QueryResult<Receiver<RowChange>> {
+        if !self.hooked.load(Ordering::Relaxed) {
+            return Err(Error::QueryBuilderError(
+                "Changes can only be watched on connections established by naphtha"
+                    .into(),
+            ));
+        }
+        c.batch_execute(&format!(
+            "SELECT rowid FROM main.{} LIMIT 0",
+            quote_identifier(table)
+        ))
+        .map_err(|e| match e {
+            Error::DatabaseError(_, ref info)
+                if info.message().starts_with("no such column") =>
+            {
+                Error::QueryBuilderError(
+                    format!(
+                        "Table {} is created WITHOUT ROWID, its changes cannot be watched",
+                        table
+                    )
+                    .into(),
+                )
+            }
+            e => e,
+        })?;
+
+        let (sender, receiver) = mpsc::channel();
+        self.senders
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(table.to_string())
+            .or_default()
+            .push(sender);
+        Ok(receiver)
+    }
+
+    /// Sends the change to all watchers of the table, those that dropped
+    /// their receiver are removed.
+    fn notify(&self, table: &str, operation: RowOperation, rowid: i64) {
+        let mut senders =
+            self.senders.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(senders) = senders.get_mut(table) {
+            let change = RowChange {
+                table: table.to_string(),
+                operation,
+                rowid,
+            };
+            senders.retain(|sender| sender.send(change.clone()).is_ok());
+        }
+    }
+}
+
+/// Auto extension of SQLite that stores the handle of every connection
+/// opened during [RowWatchers::establish].
+unsafe extern "C" fn capture_handle(
+    db: *mut ffi::sqlite3,
+    _error: *mut *mut c_char,
+    _api: *const c_void,
+) -> c_int {
+    let _ = OPENED.try_with(|opened| {
+        if opened.get().is_some() {
+            opened.set(Some(db));
+        }
+    });
+    ffi::SQLITE_OK
+}
+
+/// The update hook of the connections, reports the changes of the `main`
+/// database to the watchers registered under the id.
+unsafe extern "C" fn update_hook(
+    id: *mut c_void,
+    action: c_int,
+    database: *const c_char,
+    table: *const c_char,
+    rowid: i64,
+) {
+    let operation = match RowOperation::from_action(action) {
+        Some(operation) => operation,
+        None => return,
+    };
+    if CStr::from_ptr(database).to_bytes() != b"main" {
+        return;
+    }
+    let watchers = HOOKED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&(id as usize))
+        .and_then(Weak::upgrade);
+    if let Some(watchers) = watchers {
+        watchers.notify(
+            &CStr::from_ptr(table).to_string_lossy(),
+            operation,
+            rowid,
+        );
+    }
+}
+
+/// Quotes the given SQL identifier.
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
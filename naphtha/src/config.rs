@@ -0,0 +1,621 @@
+//! Structured configuration of a database connection.
+//!
+//! A [DatabaseConfig] can be loaded from environment variables, a TOML file
+//! (requires the `toml` feature) or be created in code. It is then used to
+//! establish the connection:
+//!
+//! ```ignore
+//! use naphtha::{config::DatabaseConfig, DatabaseConnect, DatabaseConnection};
+//! let config = DatabaseConfig::from_env()?;
+//! let db: DatabaseConnection<AnyConnection> =
+//!     DatabaseConnection::connect_config(&config)?;
+//! ```
+//!
+//! ## Pool size
+//!
+//! The [pool size](DatabaseConfig::pool_size) is the maximum number of
+//! connections that are established by
+//! [connect_config](crate::DatabaseConnect::connect_config). A
+//! [DatabaseConnection](crate::DatabaseConnection) currently holds one
+//! connection to the primary and one to each replica, so the pool size has to
+//! be at least the number of replicas plus one. Connections to the primary are
+//! not pooled yet.
+
+use {
+    crate::{PoisonPolicy, SlowQueryLog},
+    std::{fmt, path::Path, time::Duration},
+};
+
+/// The configuration of a database connection.
+///
+/// Secrets can be read from files, e.g. the ones mounted by docker or
+/// kubernetes, instead of being part of the URL.
+#[derive(Clone, PartialEq, Eq)]
+pub struct DatabaseConfig {
+    url: String,
+    replica_urls: Vec<String>,
+    password: Option<String>,
+    connect_timeout: Option<Duration>,
+    busy_timeout: Option<Duration>,
+    lock_timeout: Option<Duration>,
+    pool_size: Option<usize>,
+    poison_policy: PoisonPolicy,
+    slow_query_threshold: Option<Duration>,
+    sqlite_pragmas: Vec<(String, String)>,
+    migrate: bool,
+}
+
+impl DatabaseConfig {
+    const ENV_PREFIX: &'static str = "DATABASE_";
+
+    /// Creates a new configuration for the given database URL.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            replica_urls: vec![],
+            password: None,
+            connect_timeout: None,
+            busy_timeout: None,
+            lock_timeout: None,
+            pool_size: None,
+            poison_policy: PoisonPolicy::default(),
+            slow_query_threshold: None,
+            sqlite_pragmas: vec![],
+            migrate: false,
+        }
+    }
+
+    /// Adds the URL of a read replica, see
+    /// [with_replica](crate::DatabaseConnection::with_replica). The password
+    /// and connection parameters are applied to the replicas as well.
+    pub fn replica(mut self, url: &str) -> Self {
+        self.replica_urls.push(url.to_string());
+        self
+    }
+
+    /// Sets the password that is inserted into the URL. It is percent encoded,
+    /// so it may contain any character.
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(password.to_string());
+        self
+    }
+
+    /// Reads the password from the given file. A trailing line break is
+    /// removed.
+    pub fn password_file<P: AsRef<Path>>(
+        self,
+        path: P,
+    ) -> anyhow::Result<Self> {
+        let password = read_secret(path.as_ref())?;
+        Ok(self.password(&password))
+    }
+
+    /// Sets the timeout for establishing the connection.
+    ///
+    /// *Only supported by PostgreSQL, it is ignored by the other backends.*
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the time SQLite waits for a locked database before returning an
+    /// error.
+    pub fn busy_timeout(mut self, timeout: Duration) -> Self {
+        self.busy_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum time the model operations wait for the lock of the
+    /// connection, see [with_lock_timeout](crate::DatabaseConnection::with_lock_timeout).
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum number of connections that are established, see
+    /// [Pool size](crate::config#pool-size). It is not limited by default.
+    pub fn pool_size(mut self, size: usize) -> Self {
+        self.pool_size = Some(size);
+        self
+    }
+
+    /// Sets the policy for handling a poisoned connection lock, see
+    /// [with_poison_policy](crate::DatabaseConnection::with_poison_policy).
+    pub fn poison_policy(mut self, poison_policy: PoisonPolicy) -> Self {
+        self.poison_policy = poison_policy;
+        self
+    }
+
+    /// Enables logging the statements of the model operations that take at
+    /// least the given time, see [SlowQueryLog].
+    pub fn slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_query_threshold = Some(threshold);
+        self
+    }
+
+    /// Adds a `PRAGMA` that is executed after connecting to SQLite, e.g.
+    /// `sqlite_pragma("foreign_keys", "ON")`.
+    pub fn sqlite_pragma(mut self, name: &str, value: &str) -> Self {
+        self.sqlite_pragmas
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Defines if the migrations should be executed, see
+    /// [migrate_up](DatabaseConfig::migrate_up).
+    pub fn migrate(mut self, migrate: bool) -> Self {
+        self.migrate = migrate;
+        self
+    }
+
+    /// Loads the configuration from the following environment variables:
+    ///
+    /// * `DATABASE_URL` (required)
+    /// * `DATABASE_REPLICA_URLS`, comma separated
+    /// * `DATABASE_PASSWORD`
+    /// * `DATABASE_CONNECT_TIMEOUT` in seconds
+    /// * `DATABASE_BUSY_TIMEOUT` in milliseconds
+    /// * `DATABASE_LOCK_TIMEOUT` in milliseconds
+    /// * `DATABASE_POOL_SIZE`
+    /// * `DATABASE_POISON_POLICY`, either `fail`, `recover` or `reconnect`
+    /// * `DATABASE_SLOW_QUERY_THRESHOLD` in milliseconds
+    /// * `DATABASE_SQLITE_PRAGMAS`, e.g. `foreign_keys=ON;journal_mode=WAL`
+    /// * `DATABASE_MIGRATE`, either `true` or `false`
+    ///
+    /// Every variable can also be read from a file given by the same name
+    /// with `_FILE` appended, e.g. `DATABASE_PASSWORD_FILE`.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let url = env_var("URL")?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Missing environment variable {}URL!",
+                Self::ENV_PREFIX
+            )
+        })?;
+        let mut config = Self::new(&url);
+        if let Some(urls) = env_var("REPLICA_URLS")? {
+            for url in urls.split(',').filter(|u| !u.trim().is_empty()) {
+                config = config.replica(url.trim());
+            }
+        }
+        if let Some(password) = env_var("PASSWORD")? {
+            config = config.password(&password);
+        }
+        if let Some(timeout) = env_var("CONNECT_TIMEOUT")? {
+            config = config.connect_timeout(Duration::from_secs(parse(
+                "CONNECT_TIMEOUT",
+                &timeout,
+            )?));
+        }
+        if let Some(timeout) = env_var("BUSY_TIMEOUT")? {
+            config = config.busy_timeout(Duration::from_millis(parse(
+                "BUSY_TIMEOUT",
+                &timeout,
+            )?));
+        }
+        if let Some(timeout) = env_var("LOCK_TIMEOUT")? {
+            config = config.lock_timeout(Duration::from_millis(parse(
+                "LOCK_TIMEOUT",
+                &timeout,
+            )?));
+        }
+        if let Some(size) = env_var("POOL_SIZE")? {
+            config = config.pool_size(parse("POOL_SIZE", &size)?);
+        }
+        if let Some(policy) = env_var("POISON_POLICY")? {
+            config = config.poison_policy(parse("POISON_POLICY", &policy)?);
+        }
+        if let Some(threshold) = env_var("SLOW_QUERY_THRESHOLD")? {
+            config = config.slow_query_threshold(Duration::from_millis(parse(
+                "SLOW_QUERY_THRESHOLD",
+                &threshold,
+            )?));
+        }
+        if let Some(pragmas) = env_var("SQLITE_PRAGMAS")? {
+            for pragma in pragmas.split(';').filter(|p| !p.trim().is_empty()) {
+                let (name, value) = pragma.split_once('=').ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Invalid pragma '{}' in {}SQLITE_PRAGMAS, expected name=value!",
+                        pragma,
+                        Self::ENV_PREFIX
+                    )
+                })?;
+                config = config.sqlite_pragma(name.trim(), value.trim());
+            }
+        }
+        if let Some(migrate) = env_var("MIGRATE")? {
+            config = config.migrate(parse("MIGRATE", &migrate)?);
+        }
+        Ok(config)
+    }
+
+    /// Loads the configuration from the given TOML file, see
+    /// [from_toml_str](DatabaseConfig::from_toml_str).
+    ///
+    /// *Requires the `toml` feature.*
+    #[cfg(feature = "toml")]
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+            anyhow::anyhow!("Could not read {}: {}", path.as_ref().display(), e)
+        })?;
+        Self::from_toml_str(&content)
+    }
+
+    /// Loads the configuration from the given TOML document:
+    ///
+    /// ```toml
+    /// url = "postgres://naphtha@127.0.0.1:5432/naphtha"
+    /// replica_urls = ["postgres://naphtha@127.0.0.2:5432/naphtha"]
+    /// password_file = "/run/secrets/database_password"
+    /// connect_timeout = 5 # seconds
+    /// busy_timeout = 1000 # milliseconds
+    /// lock_timeout = 5000 # milliseconds
+    /// pool_size = 4
+    /// poison_policy = "recover"
+    /// slow_query_threshold = 200 # milliseconds
+    /// migrate = true
+    ///
+    /// [sqlite_pragmas]
+    /// foreign_keys = "ON"
+    /// ```
+    ///
+    /// The `url` and `password` can also be read from a file by `url_file`
+    /// and `password_file`.
+    ///
+    /// *Requires the `toml` feature.*
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(content: &str) -> anyhow::Result<Self> {
+        use toml_dep::Value;
+
+        let table = match content.parse::<Value>()? {
+            Value::Table(table) => table,
+            _ => return Err(anyhow::anyhow!("Expected a TOML table!")),
+        };
+        let string = |key: &str| -> anyhow::Result<Option<String>> {
+            match table.get(key) {
+                Some(Value::String(s)) => Ok(Some(s.clone())),
+                Some(_) => {
+                    Err(anyhow::anyhow!("Expected a string for {}!", key))
+                }
+                None => match table.get(&format!("{}_file", key)) {
+                    Some(Value::String(path)) => {
+                        Ok(Some(read_secret(Path::new(path))?))
+                    }
+                    Some(_) => Err(anyhow::anyhow!(
+                        "Expected a string for {}_file!",
+                        key
+                    )),
+                    None => Ok(None),
+                },
+            }
+        };
+        let integer = |key: &str| -> anyhow::Result<Option<u64>> {
+            match table.get(key) {
+                Some(Value::Integer(i)) if *i >= 0 => Ok(Some(*i as u64)),
+                Some(_) => Err(anyhow::anyhow!(
+                    "Expected a positive integer for {}!",
+                    key
+                )),
+                None => Ok(None),
+            }
+        };
+
+        let url = string("url")?
+            .ok_or_else(|| anyhow::anyhow!("Missing url in configuration!"))?;
+        let mut config = Self::new(&url);
+        match table.get("replica_urls") {
+            Some(Value::Array(urls)) => {
+                for url in urls {
+                    match url {
+                        Value::String(url) => config = config.replica(url),
+                        _ => {
+                            return Err(anyhow::anyhow!(
+                                "Expected strings in replica_urls!"
+                            ))
+                        }
+                    }
+                }
+            }
+            Some(_) => {
+                return Err(anyhow::anyhow!(
+                    "Expected an array for replica_urls!"
+                ))
+            }
+            None => (),
+        }
+        if let Some(password) = string("password")? {
+            config = config.password(&password);
+        }
+        if let Some(timeout) = integer("connect_timeout")? {
+            config = config.connect_timeout(Duration::from_secs(timeout));
+        }
+        if let Some(timeout) = integer("busy_timeout")? {
+            config = config.busy_timeout(Duration::from_millis(timeout));
+        }
+        if let Some(timeout) = integer("lock_timeout")? {
+            config = config.lock_timeout(Duration::from_millis(timeout));
+        }
+        if let Some(size) = integer("pool_size")? {
+            config = config.pool_size(size as usize);
+        }
+        if let Some(policy) = string("poison_policy")? {
+            config = config.poison_policy(
+                policy.parse().map_err(|e: String| anyhow::anyhow!(e))?,
+            );
+        }
+        if let Some(threshold) = integer("slow_query_threshold")? {
+            config =
+                config.slow_query_threshold(Duration::from_millis(threshold));
+        }
+        match table.get("sqlite_pragmas") {
+            Some(Value::Table(pragmas)) => {
+                for (name, value) in pragmas {
+                    let value = match value {
+                        Value::String(s) => s.clone(),
+                        v => v.to_string(),
+                    };
+                    config = config.sqlite_pragma(name, &value);
+                }
+            }
+            Some(_) => {
+                return Err(anyhow::anyhow!(
+                    "Expected a table for sqlite_pragmas!"
+                ))
+            }
+            None => (),
+        }
+        match table.get("migrate") {
+            Some(Value::Boolean(migrate)) => config = config.migrate(*migrate),
+            Some(_) => {
+                return Err(anyhow::anyhow!("Expected a boolean for migrate!"))
+            }
+            None => (),
+        }
+        Ok(config)
+    }
+
+    /// Returns the URL including the password and the parameters supported by
+    /// the database backend.
+    pub fn database_url(&self) -> anyhow::Result<String> {
+        self.complete_url(&self.url)
+    }
+
+    /// Returns the URLs of the read replicas, completed the same way as
+    /// [database_url](DatabaseConfig::database_url).
+    pub fn replica_urls(&self) -> anyhow::Result<Vec<String>> {
+        self.replica_urls
+            .iter()
+            .map(|url| self.complete_url(url))
+            .collect()
+    }
+
+    /// Returns the maximum number of connections that are established, if
+    /// limited.
+    pub fn max_connections(&self) -> Option<usize> {
+        self.pool_size
+    }
+
+    /// Returns an error if the pool size is too small for the primary and its
+    /// replicas.
+    pub(crate) fn check_pool_size(&self) -> anyhow::Result<()> {
+        let required = self.replica_urls.len() + 1;
+        match self.pool_size {
+            Some(size) if size < required => Err(anyhow::anyhow!(
+                "The pool size {} is too small for the primary and {} replicas, at least {} connections are required!",
+                size,
+                self.replica_urls.len(),
+                required
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn complete_url(&self, url: &str) -> anyhow::Result<String> {
+        let mut url = match &self.password {
+            Some(password) => insert_password(url, password)?,
+            None => url.to_string(),
+        };
+        if let Some(timeout) = self.connect_timeout {
+            if url.starts_with("postgres://")
+                || url.starts_with("postgresql://")
+            {
+                let separator = if url.contains('?') { '&' } else { '?' };
+                url = format!(
+                    "{}{}connect_timeout={}",
+                    url,
+                    separator,
+                    timeout.as_secs().max(1)
+                );
+            }
+        }
+        Ok(url)
+    }
+
+    /// Returns the statements that are executed after connecting to SQLite.
+    pub fn sqlite_statements(&self) -> Vec<String> {
+        let mut statements = vec![];
+        if let Some(timeout) = self.busy_timeout {
+            statements.push(format!(
+                "PRAGMA busy_timeout = {};",
+                timeout.as_millis()
+            ));
+        }
+        for (name, value) in self.sqlite_pragmas.iter() {
+            statements.push(format!("PRAGMA {} = {};", name, value));
+        }
+        statements
+    }
+
+    /// Applies the settings that are not part of the URL to the connection.
+    pub(crate) fn configure<T>(
+        &self,
+        connection: crate::DatabaseConnection<T>,
+    ) -> crate::DatabaseConnection<T> {
+        let mut connection = connection.with_poison_policy(self.poison_policy);
+        if let Some(timeout) = self.lock_timeout {
+            connection = connection.with_lock_timeout(timeout);
+        }
+        if let Some(threshold) = self.slow_query_threshold {
+            connection =
+                connection.with_slow_query_log(SlowQueryLog::new(threshold));
+        }
+        connection
+    }
+
+    /// Returns `true` if the migrations should be executed.
+    pub fn is_migration_enabled(&self) -> bool {
+        self.migrate
+    }
+
+    /// Executes the up migration of the given model if enabled by
+    /// [migrate](DatabaseConfig::migrate). Does nothing otherwise.
+    ///
+    /// *Requires any of the barrel features.*
+    #[cfg(any(
+        feature = "barrel-sqlite",
+        feature = "barrel-mysql",
+        feature = "barrel-pg"
+    ))]
+    pub fn migrate_up<M, T>(
+        &self,
+        conn: &crate::DatabaseConnection<T>,
+    ) -> Result<(), String>
+    where
+        M: crate::barrel::DatabaseSqlMigrationExecutor<T, usize>,
+    {
+        if self.migrate {
+            M::execute_migration_up(conn)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DatabaseConfig {
+    // The passwords are not shown to prevent leaking them into logs, neither
+    // the separate one nor the ones in the URLs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let replica_urls = self
+            .replica_urls
+            .iter()
+            .map(|url| redact_password(url))
+            .collect::<Vec<_>>();
+        f.debug_struct("DatabaseConfig")
+            .field("url", &redact_password(&self.url))
+            .field("replica_urls", &replica_urls)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("connect_timeout", &self.connect_timeout)
+            .field("busy_timeout", &self.busy_timeout)
+            .field("lock_timeout", &self.lock_timeout)
+            .field("pool_size", &self.pool_size)
+            .field("poison_policy", &self.poison_policy)
+            .field("slow_query_threshold", &self.slow_query_threshold)
+            .field("sqlite_pragmas", &self.sqlite_pragmas)
+            .field("migrate", &self.migrate)
+            .finish()
+    }
+}
+
+/// Reads the environment variable with the given name or the file given by
+/// the variable with `_FILE` appended.
+fn env_var(name: &str) -> anyhow::Result<Option<String>> {
+    let name = format!("{}{}", DatabaseConfig::ENV_PREFIX, name);
+    if let Ok(value) = std::env::var(&name) {
+        return Ok(Some(value));
+    }
+    match std::env::var(format!("{}_FILE", name)) {
+        Ok(path) => Ok(Some(read_secret(Path::new(&path))?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Parses the value of the given environment variable.
+fn parse<T: std::str::FromStr>(name: &str, value: &str) -> anyhow::Result<T>
+where
+    T::Err: fmt::Display,
+{
+    value.trim().parse().map_err(|e| {
+        anyhow::anyhow!(
+            "Invalid value for {}{}: {}",
+            DatabaseConfig::ENV_PREFIX,
+            name,
+            e
+        )
+    })
+}
+
+/// Reads a secret from the given file, removing a trailing line break.
+fn read_secret(path: &Path) -> anyhow::Result<String> {
+    let secret = std::fs::read_to_string(path).map_err(|e| {
+        anyhow::anyhow!("Could not read {}: {}", path.display(), e)
+    })?;
+    Ok(secret.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+/// Returns the positions of the start of the user info and of the `@`
+/// ending it, if the URL has a user info.
+fn user_info(url: &str) -> Option<(usize, usize)> {
+    let authority_start = url.find("://")? + 3;
+    let authority_end = url[authority_start..]
+        .find(&['/', '?'][..])
+        .map(|i| authority_start + i)
+        .unwrap_or(url.len());
+    let user_end = url[authority_start..authority_end].rfind('@')?;
+    Some((authority_start, authority_start + user_end))
+}
+
+/// Inserts the percent encoded password into the user info of the URL.
+fn insert_password(url: &str, password: &str) -> anyhow::Result<String> {
+    if !url.contains("://") {
+        return Err(anyhow::anyhow!(
+            "A password requires a database URL with scheme!"
+        ));
+    }
+    let (user_start, user_end) = user_info(url).ok_or_else(|| {
+        anyhow::anyhow!(
+            "A password requires a user in the database URL, e.g. mysql://user@host/database!"
+        )
+    })?;
+    let user = &url[user_start..user_end];
+    let user = user.split(':').next().unwrap_or(user);
+    Ok(format!(
+        "{}{}:{}{}",
+        &url[..user_start],
+        user,
+        percent_encode(password),
+        &url[user_end..]
+    ))
+}
+
+/// Replaces the password in the user info of the URL by `***`.
+fn redact_password(url: &str) -> String {
+    match user_info(url) {
+        Some((user_start, user_end)) => {
+            match url[user_start..user_end].find(':') {
+                Some(i) => format!(
+                    "{}***{}",
+                    &url[..user_start + i + 1],
+                    &url[user_end..]
+                ),
+                None => url.to_string(),
+            }
+        }
+        None => url.to_string(),
+    }
+}
+
+/// Percent encodes all characters except the unreserved ones of RFC 3986.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
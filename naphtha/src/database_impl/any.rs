@@ -0,0 +1,177 @@
+#[cfg(feature = "mysql")]
+use diesel::MysqlConnection;
+#[cfg(feature = "pg")]
+use diesel::PgConnection;
+#[cfg(feature = "sqlite")]
+use diesel::SqliteConnection;
+use {
+    crate::{
+        DatabaseConfig,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseHealthCheck,
+    },
+    diesel::{result::QueryResult, Connection},
+    std::sync::{Arc, Mutex},
+};
+
+/// A connection to one of the enabled database backends that is selected at
+/// runtime by the scheme of the database URL.
+///
+/// The models implement all traits for [AnyConnection] by dispatching to the
+/// wrapped connection. Custom queries need to match the backend:
+///
+/// ```ignore
+/// db.custom(|c| match c {
+///     AnyConnection::Sqlite(c) => persons::table.load::<Person>(c),
+///     AnyConnection::Pg(c) => persons::table.load::<Person>(c),
+/// });
+/// ```
+pub enum AnyConnection {
+    /// A SQLite connection, selected by `sqlite://` or a URL without scheme.
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteConnection),
+    /// A MySQL connection, selected by `mysql://`.
+    #[cfg(feature = "mysql")]
+    Mysql(MysqlConnection),
+    /// A PostgreSQL connection, selected by `postgres://` or `postgresql://`.
+    #[cfg(feature = "pg")]
+    Pg(PgConnection),
+}
+
+impl AnyConnection {
+    /// Establishes a connection to the backend given by the scheme of the
+    /// database URL. URLs without scheme, e.g. a file path or `:memory:`, are
+    /// opened by SQLite.
+    pub fn establish(database_url: &str) -> anyhow::Result<Self> {
+        let scheme = match database_url.find("://") {
+            Some(i) => &database_url[..i],
+            None => "",
+        };
+        match scheme {
+            #[cfg(feature = "sqlite")]
+            "" | "sqlite" => {
+                let path = database_url
+                    .strip_prefix("sqlite://")
+                    .unwrap_or(database_url);
+                Ok(AnyConnection::Sqlite(Connection::establish(path)?))
+            }
+            #[cfg(feature = "mysql")]
+            "mysql" => {
+                Ok(AnyConnection::Mysql(Connection::establish(database_url)?))
+            }
+            #[cfg(feature = "pg")]
+            "postgres" | "postgresql" => {
+                Ok(AnyConnection::Pg(Connection::establish(database_url)?))
+            }
+            // the backend is known but its feature is not enabled
+            #[allow(unreachable_patterns)]
+            "" | "sqlite" | "mysql" | "postgres" | "postgresql" => {
+                Err(anyhow::anyhow!(
+                    "Could not connect to {}, the feature of the database backend is not enabled!",
+                    database_url
+                ))
+            }
+            scheme => Err(anyhow::anyhow!(
+                "Unknown database scheme '{}'! Possible values are: sqlite, mysql, postgres, postgresql",
+                scheme
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<SqliteConnection> for AnyConnection {
+    fn from(c: SqliteConnection) -> Self {
+        AnyConnection::Sqlite(c)
+    }
+}
+
+#[cfg(feature = "mysql")]
+impl From<MysqlConnection> for AnyConnection {
+    fn from(c: MysqlConnection) -> Self {
+        AnyConnection::Mysql(c)
+    }
+}
+
+#[cfg(feature = "pg")]
+impl From<PgConnection> for AnyConnection {
+    fn from(c: PgConnection) -> Self {
+        AnyConnection::Pg(c)
+    }
+}
+
+impl From<AnyConnection> for DatabaseConnection<AnyConnection> {
+    fn from(c: AnyConnection) -> Self {
+        DatabaseConnection::<AnyConnection>::new(Arc::new(Mutex::new(c)))
+    }
+}
+
+impl From<Arc<Mutex<AnyConnection>>> for DatabaseConnection<AnyConnection> {
+    fn from(c: Arc<Mutex<AnyConnection>>) -> Self {
+        DatabaseConnection::<AnyConnection>::new(c)
+    }
+}
+
+impl DatabaseConnect<AnyConnection> for DatabaseConnection<AnyConnection> {
+    fn connect(
+        database_url: &str,
+    ) -> anyhow::Result<DatabaseConnection<AnyConnection>> {
+        let url = database_url.to_string();
+        let establish = move || AnyConnection::establish(&url);
+        Ok(DatabaseConnection::from(establish()?).with_connector(establish))
+    }
+
+    fn connect_config(
+        config: &DatabaseConfig,
+    ) -> anyhow::Result<DatabaseConnection<AnyConnection>> {
+        config.check_pool_size()?;
+        let connect = |url: String| -> anyhow::Result<_> {
+            #[cfg(feature = "sqlite")]
+            let statements = config.sqlite_statements();
+            let establish = move || -> anyhow::Result<AnyConnection> {
+                let connection = AnyConnection::establish(&url)?;
+                #[cfg(feature = "sqlite")]
+                #[allow(irrefutable_let_patterns)]
+                if let AnyConnection::Sqlite(c) = &connection {
+                    use diesel::connection::SimpleConnection;
+                    for statement in statements.iter() {
+                        c.batch_execute(statement)?;
+                    }
+                }
+                Ok(connection)
+            };
+            let connection = DatabaseConnection::from(establish()?);
+            Ok(connection.with_connector(establish))
+        };
+        let mut connection = connect(config.database_url()?)?;
+        for url in config.replica_urls()? {
+            connection = connection.with_replica(connect(url)?);
+        }
+        Ok(config.configure(connection))
+    }
+}
+
+impl DatabaseHealthCheck for AnyConnection {
+    fn ping(&self) -> QueryResult<()> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            AnyConnection::Sqlite(c) => c.ping(),
+            #[cfg(feature = "mysql")]
+            AnyConnection::Mysql(c) => c.ping(),
+            #[cfg(feature = "pg")]
+            AnyConnection::Pg(c) => c.ping(),
+        }
+    }
+
+    fn rollback_transactions(&self) -> QueryResult<()> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            AnyConnection::Sqlite(c) => c.rollback_transactions(),
+            #[cfg(feature = "mysql")]
+            AnyConnection::Mysql(c) => c.rollback_transactions(),
+            #[cfg(feature = "pg")]
+            AnyConnection::Pg(c) => c.rollback_transactions(),
+        }
+    }
+}
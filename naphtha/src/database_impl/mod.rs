@@ -1,14 +1,13 @@
 #[cfg(feature = "sqlite")]
 mod sqlite;
-#[cfg(feature = "sqlite")]
-pub use sqlite::*;
 
 #[cfg(feature = "mysql")]
 mod mysql;
-#[cfg(feature = "mysql")]
-pub use mysql::*;
 
 #[cfg(feature = "pg")]
 mod pg;
-#[cfg(feature = "pg")]
-pub use pg::*;
+
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+mod any;
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+pub use any::*;
@@ -1,18 +1,30 @@
 use {
-    crate::{DatabaseConnect, DatabaseConnection},
-    diesel::{Connection, MysqlConnection},
+    crate::{
+        slow_query::CountBinds,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseHealthCheck,
+    },
+    diesel::{
+        mysql::{Mysql, MysqlQueryBuilder},
+        query_builder::{QueryBuilder, QueryFragment},
+        result::QueryResult,
+        Connection,
+        MysqlConnection,
+        RunQueryDsl,
+    },
     std::sync::{Arc, Mutex},
 };
 
 impl From<MysqlConnection> for DatabaseConnection<MysqlConnection> {
     fn from(c: MysqlConnection) -> Self {
-        DatabaseConnection::<MysqlConnection>(Arc::new(Mutex::new(c)))
+        DatabaseConnection::<MysqlConnection>::new(Arc::new(Mutex::new(c)))
     }
 }
 
 impl From<Arc<Mutex<MysqlConnection>>> for DatabaseConnection<MysqlConnection> {
     fn from(c: Arc<Mutex<MysqlConnection>>) -> Self {
-        DatabaseConnection::<MysqlConnection>(c)
+        DatabaseConnection::<MysqlConnection>::new(c)
     }
 }
 
@@ -20,7 +32,59 @@ impl DatabaseConnect<MysqlConnection> for DatabaseConnection<MysqlConnection> {
     fn connect(
         database_url: &str,
     ) -> anyhow::Result<DatabaseConnection<MysqlConnection>> {
-        let connection = Connection::establish(database_url)?;
-        Ok(DatabaseConnection(Arc::new(Mutex::new(connection))))
+        let url = database_url.to_string();
+        let establish = move || -> anyhow::Result<MysqlConnection> {
+            Ok(Connection::establish(&url)?)
+        };
+        Ok(DatabaseConnection::from(establish()?).with_connector(establish))
+    }
+}
+
+// the auto increment continues after the largest key
+#[cfg(feature = "serde")]
+impl crate::json::AdvanceSequence for MysqlConnection {
+    fn advance_sequence(&self, _table: &str, _column: &str) -> QueryResult<()> {
+        Ok(())
+    }
+}
+
+impl CountBinds for MysqlConnection {
+    fn count_binds<Q>(&self, query: &Q) -> QueryResult<usize>
+    where
+        Q: QueryFragment<Mysql>,
+    {
+        // The bind collector of MySQL does not expose the collected binds in
+        // diesel 1.4, so their `?` placeholders outside of quotes are counted.
+        let mut builder = MysqlQueryBuilder::new();
+        query.to_sql(&mut builder)?;
+        let mut quote = None;
+        let mut count = 0;
+        for c in builder.finish().chars() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => (),
+                None if matches!(c, '\'' | '"' | '`') => quote = Some(c),
+                None if c == '?' => count += 1,
+                None => (),
+            }
+        }
+        Ok(count)
+    }
+}
+
+impl DatabaseHealthCheck for MysqlConnection {
+    fn ping(&self) -> QueryResult<()> {
+        diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>("1"))
+            .get_result::<i32>(self)
+            .map(|_| ())
+    }
+
+    fn rollback_transactions(&self) -> QueryResult<()> {
+        use diesel::connection::TransactionManager;
+        let manager = self.transaction_manager();
+        while TransactionManager::<Self>::get_transaction_depth(manager) > 0 {
+            manager.rollback_transaction(self)?;
+        }
+        Ok(())
     }
 }
@@ -1,18 +1,30 @@
 use {
-    crate::{DatabaseConnect, DatabaseConnection},
-    diesel::{Connection, PgConnection},
+    crate::{
+        slow_query::CountBinds,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseHealthCheck,
+    },
+    diesel::{
+        pg::{Pg, PgMetadataLookup},
+        query_builder::{bind_collector::RawBytesBindCollector, QueryFragment},
+        result::QueryResult,
+        Connection,
+        PgConnection,
+        RunQueryDsl,
+    },
     std::sync::{Arc, Mutex},
 };
 
 impl From<PgConnection> for DatabaseConnection<PgConnection> {
     fn from(c: PgConnection) -> Self {
-        DatabaseConnection::<PgConnection>(Arc::new(Mutex::new(c)))
+        DatabaseConnection::<PgConnection>::new(Arc::new(Mutex::new(c)))
     }
 }
 
 impl From<Arc<Mutex<PgConnection>>> for DatabaseConnection<PgConnection> {
     fn from(c: Arc<Mutex<PgConnection>>) -> Self {
-        DatabaseConnection::<PgConnection>(c)
+        DatabaseConnection::<PgConnection>::new(c)
     }
 }
 
@@ -20,7 +32,63 @@ impl DatabaseConnect<PgConnection> for DatabaseConnection<PgConnection> {
     fn connect(
         database_url: &str,
     ) -> anyhow::Result<DatabaseConnection<PgConnection>> {
-        let connection = Connection::establish(database_url)?;
-        Ok(DatabaseConnection(Arc::new(Mutex::new(connection))))
+        let url = database_url.to_string();
+        let establish = move || -> anyhow::Result<PgConnection> {
+            Ok(Connection::establish(&url)?)
+        };
+        Ok(DatabaseConnection::from(establish()?).with_connector(establish))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl crate::json::AdvanceSequence for PgConnection {
+    fn advance_sequence(&self, table: &str, column: &str) -> QueryResult<()> {
+        let quote = |identifier: &str| {
+            format!("\"{}\"", identifier.replace('"', "\"\""))
+        };
+        // setval is skipped for empty tables and columns without sequence,
+        // as it returns NULL on NULL arguments
+        diesel::sql_query(format!(
+            "SELECT setval(pg_get_serial_sequence($1, $2), MAX({column})) FROM {table}",
+            column = quote(column),
+            table = quote(table),
+        ))
+        .bind::<diesel::sql_types::Text, _>(quote(table))
+        .bind::<diesel::sql_types::Text, _>(column)
+        .execute(self)
+        .map(|_| ())
+    }
+}
+
+impl CountBinds for PgConnection {
+    fn count_binds<Q>(&self, query: &Q) -> QueryResult<usize>
+    where
+        Q: QueryFragment<Pg>,
+    {
+        // SAFETY: diesel 1.4 defines `PgMetadataLookup` as transparent wrapper
+        // of the connection, but only creates it internally by the same cast.
+        let lookup = unsafe {
+            &*(self as *const PgConnection as *const PgMetadataLookup)
+        };
+        let mut collector = RawBytesBindCollector::<Pg>::new();
+        query.collect_binds(&mut collector, lookup)?;
+        Ok(collector.binds.len())
+    }
+}
+
+impl DatabaseHealthCheck for PgConnection {
+    fn ping(&self) -> QueryResult<()> {
+        diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>("1"))
+            .get_result::<i32>(self)
+            .map(|_| ())
+    }
+
+    fn rollback_transactions(&self) -> QueryResult<()> {
+        use diesel::connection::TransactionManager;
+        let manager = self.transaction_manager();
+        while TransactionManager::<Self>::get_transaction_depth(manager) > 0 {
+            manager.rollback_transaction(self)?;
+        }
+        Ok(())
     }
 }
@@ -1,12 +1,29 @@
 use {
-    crate::{DatabaseConnect, DatabaseConnection},
-    diesel::{Connection, SqliteConnection},
-    std::sync::{Arc, Mutex},
+    crate::{
+        changes::{RowChange, RowWatchers},
+        slow_query::CountBinds,
+        ConnectionError,
+        DatabaseConfig,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseHealthCheck,
+        DatabaseModel,
+    },
+    diesel::{
+        connection::SimpleConnection,
+        query_builder::{bind_collector::RawBytesBindCollector, QueryFragment},
+        result::QueryResult,
+        sqlite::Sqlite,
+        Connection,
+        RunQueryDsl,
+        SqliteConnection,
+    },
+    std::sync::{mpsc::Receiver, Arc, Mutex},
 };
 
 impl From<SqliteConnection> for DatabaseConnection<SqliteConnection> {
     fn from(c: SqliteConnection) -> Self {
-        DatabaseConnection::<SqliteConnection>(Arc::new(Mutex::new(c)))
+        DatabaseConnection::<SqliteConnection>::new(Arc::new(Mutex::new(c)))
     }
 }
 
@@ -14,7 +31,27 @@ impl From<Arc<Mutex<SqliteConnection>>>
     for DatabaseConnection<SqliteConnection>
 {
     fn from(c: Arc<Mutex<SqliteConnection>>) -> Self {
-        DatabaseConnection::<SqliteConnection>(c)
+        DatabaseConnection::<SqliteConnection>::new(c)
+    }
+}
+
+impl DatabaseConnection<SqliteConnection> {
+    /// Returns a receiver of all changes to the rows of the given table made
+    /// on the primary connection, see the [changes](crate::changes) module.
+    /// The watch ends when the receiver is dropped.
+    pub fn watch_table(
+        &self,
+        table: &str,
+    ) -> Result<Receiver<RowChange>, ConnectionError> {
+        self.write(|c| self.row_watchers.watch(c, table))
+    }
+
+    /// Returns a receiver of all changes to the rows of the table of the
+    /// model `M`, see [watch_table](DatabaseConnection::watch_table).
+    pub fn watch<M: DatabaseModel>(
+        &self,
+    ) -> Result<Receiver<RowChange>, ConnectionError> {
+        self.watch_table(M::table_name())
     }
 }
 
@@ -24,7 +61,80 @@ impl DatabaseConnect<SqliteConnection>
     fn connect(
         database_url: &str,
     ) -> anyhow::Result<DatabaseConnection<SqliteConnection>> {
-        let connection = Connection::establish(database_url)?;
-        Ok(DatabaseConnection(Arc::new(Mutex::new(connection))))
+        let url = database_url.to_string();
+        let watchers = Arc::<RowWatchers>::default();
+        let establish = {
+            let watchers = Arc::clone(&watchers);
+            move || watchers.establish(&url)
+        };
+        let mut connection =
+            DatabaseConnection::from(establish()?).with_connector(establish);
+        connection.row_watchers = watchers;
+        Ok(connection)
+    }
+
+    fn connect_config(
+        config: &DatabaseConfig,
+    ) -> anyhow::Result<DatabaseConnection<SqliteConnection>> {
+        config.check_pool_size()?;
+        let connect = |url: String| -> anyhow::Result<_> {
+            let statements = config.sqlite_statements();
+            let watchers = Arc::<RowWatchers>::default();
+            let establish = {
+                let watchers = Arc::clone(&watchers);
+                move || -> anyhow::Result<SqliteConnection> {
+                    let connection = watchers.establish(&url)?;
+                    for statement in statements.iter() {
+                        connection.batch_execute(statement)?;
+                    }
+                    Ok(connection)
+                }
+            };
+            let mut connection = DatabaseConnection::from(establish()?)
+                .with_connector(establish);
+            connection.row_watchers = watchers;
+            Ok(connection)
+        };
+        let mut connection = connect(config.database_url()?)?;
+        for url in config.replica_urls()? {
+            connection = connection.with_replica(connect(url)?);
+        }
+        Ok(config.configure(connection))
+    }
+}
+
+// the auto increment continues after the largest key
+#[cfg(feature = "serde")]
+impl crate::json::AdvanceSequence for SqliteConnection {
+    fn advance_sequence(&self, _table: &str, _column: &str) -> QueryResult<()> {
+        Ok(())
+    }
+}
+
+impl CountBinds for SqliteConnection {
+    fn count_binds<Q>(&self, query: &Q) -> QueryResult<usize>
+    where
+        Q: QueryFragment<Sqlite>,
+    {
+        let mut collector = RawBytesBindCollector::<Sqlite>::new();
+        query.collect_binds(&mut collector, &())?;
+        Ok(collector.binds.len())
+    }
+}
+
+impl DatabaseHealthCheck for SqliteConnection {
+    fn ping(&self) -> QueryResult<()> {
+        diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>("1"))
+            .get_result::<i32>(self)
+            .map(|_| ())
+    }
+
+    fn rollback_transactions(&self) -> QueryResult<()> {
+        use diesel::connection::TransactionManager;
+        let manager = self.transaction_manager();
+        while TransactionManager::<Self>::get_transaction_depth(manager) > 0 {
+            manager.rollback_transaction(self)?;
+        }
+        Ok(())
     }
 }
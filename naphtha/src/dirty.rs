@@ -0,0 +1,103 @@
+//! Tracks the changes to models having a [Snapshot] member.
+//!
+//! The member is marked by `#[naphtha(snapshot)]` and is not persisted. It
+//! holds the values of the model as they were loaded, inserted or updated
+//! last. The generated [update](crate::DatabaseModelModifier::update) then
+//! only sets the columns whose members differ from the snapshot and skips
+//! the round trip to the database if none does. Therefore concurrent
+//! updates of different members of the same row do not overwrite each
+//! other. The handlers of the update are still called in that case, changes
+//! made by [pre_update](crate::DatabaseUpdateHandler::pre_update) are saved.
+//!
+//! Without snapshot, e.g. for a model created in your application, all
+//! members are considered as changed. The persisted members are required to
+//! implement [Clone] and [PartialEq], and must not borrow, as the snapshot is
+//! `'static`.
+//!
+//! ```ignore
+//! #[model(table_name = "persons", primary_key = "id")]
+//! pub struct Person {
+//!     id: i32,
+//!     pub description: String,
+//!     pub age: i32,
+//!     #[naphtha(snapshot)]
+//!     snapshot: naphtha::dirty::Snapshot,
+//! }
+//!
+//! let mut person = Person::query_by_id(&db, &1)?;
+//! person.age += 1;
+//! assert_eq!(person.changed_fields(), ["age"]);
+//! // UPDATE persons SET age = ? WHERE id = ?
+//! person.update(&db)?;
+//! assert!(!person.is_dirty());
+//! ```
+//!
+//! Unlike models without snapshot, a changed `Option` member that is `None`
+//! sets the column to `NULL`.
+
+use std::{any::Any, fmt, sync::Arc};
+
+/// The values of a model as they were stored in the database last, see the
+/// [dirty](crate::dirty) module.
+///
+/// It is not part of the state of the model, so all snapshots are equal and
+/// the values are not printed by [Debug](fmt::Debug).
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    values: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl Snapshot {
+    /// Creates a snapshot of the given values.
+    #[doc(hidden)]
+    pub fn capture<T: Any + Send + Sync>(values: T) -> Self {
+        Self {
+            values: Some(Arc::new(values)),
+        }
+    }
+
+    /// Returns the captured values, if they are of the given type.
+    #[doc(hidden)]
+    pub fn values<T: Any>(&self) -> Option<&T> {
+        self.values.as_ref()?.downcast_ref()
+    }
+
+    /// Returns `true` if values have been captured.
+    pub fn is_captured(&self) -> bool {
+        self.values.is_some()
+    }
+}
+
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("captured", &self.is_captured())
+            .finish()
+    }
+}
+
+impl PartialEq for Snapshot {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Snapshot {}
+
+/// Compares a model to its [Snapshot], implemented for all models having
+/// one.
+pub trait DirtyTracking {
+    /// Returns the names of the members that differ from the snapshot,
+    /// excluding the primary key. These are all persisted members if there
+    /// is no snapshot.
+    fn changed_fields(&self) -> Vec<&'static str>;
+
+    /// Returns `true` if a member differs from the snapshot, so that
+    /// [update](crate::DatabaseModelModifier::update) writes to the database.
+    fn is_dirty(&self) -> bool {
+        !self.changed_fields().is_empty()
+    }
+
+    /// Replaces the snapshot by the current values of the model.
+    fn mark_clean(&mut self);
+}
@@ -0,0 +1,46 @@
+//! Errors returned by the [DatabaseConnection](crate::DatabaseConnection).
+
+use std::{fmt, time::Duration};
+
+/// The error returned by the operations executed through a
+/// [DatabaseConnection](crate::DatabaseConnection).
+#[derive(Debug)]
+pub enum ConnectionError {
+    /// The lock of the connection could not be acquired.
+    Lock(String),
+    /// The connection has still been locked after waiting the given time.
+    LockTimeout(Duration),
+    /// The query has been executed but failed.
+    Query(diesel::result::Error),
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionError::Lock(msg) => {
+                write!(f, "Could not aquire connection lock: {}", msg)
+            }
+            ConnectionError::LockTimeout(timeout) => write!(
+                f,
+                "Could not aquire connection lock within {:?}",
+                timeout
+            ),
+            ConnectionError::Query(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ConnectionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConnectionError::Lock(_) | ConnectionError::LockTimeout(_) => None,
+            ConnectionError::Query(e) => Some(e),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for ConnectionError {
+    fn from(e: diesel::result::Error) -> Self {
+        ConnectionError::Query(e)
+    }
+}
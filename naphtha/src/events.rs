@@ -0,0 +1,183 @@
+//! In-process notifications about the changes to models marked by `events`.
+//!
+//! The generated [insert](crate::DatabaseModelModifier::insert),
+//! [update](crate::DatabaseModelModifier::update) and
+//! [remove](crate::DatabaseModelModifier::remove) of such a model publish a
+//! [ModelEvent] to all receivers returned by
+//! [subscribe](crate::DatabaseConnection::subscribe) after the change is
+//! committed. Failed operations and removing a missing model do not publish
+//! anything.
+//!
+//! ```ignore
+//! #[model(table_name = "persons", primary_key = "id", events)]
+//! #[derive(Clone)]
+//! pub struct Person {
+//!     id: i32,
+//!     pub description: String,
+//! }
+//!
+//! let events = db.subscribe::<Person>();
+//! std::thread::spawn(move || {
+//!     for event in events {
+//!         invalidate_cache(event.primary_key());
+//!     }
+//! });
+//! ```
+
+use {
+    crate::DatabaseModel,
+    std::{
+        any::{Any, TypeId},
+        collections::HashMap,
+        fmt,
+        sync::{
+            mpsc::{self, Receiver, Sender},
+            Mutex,
+            PoisonError,
+        },
+    },
+};
+
+/// A committed change of a model.
+pub enum ModelEvent<M: DatabaseModel> {
+    /// The model has been inserted, the primary key is the assigned one.
+    Inserted {
+        /// The model as it was inserted.
+        model: M,
+        /// The primary key of the model.
+        primary_key: M::PrimaryKey,
+    },
+    /// The model has been updated.
+    Updated {
+        /// The model as it was updated.
+        model: M,
+        /// The primary key of the model.
+        primary_key: M::PrimaryKey,
+    },
+    /// The model has been removed.
+    Removed {
+        /// The model as it was removed.
+        model: M,
+        /// The primary key of the model.
+        primary_key: M::PrimaryKey,
+    },
+}
+
+impl<M: DatabaseModel> ModelEvent<M> {
+    /// Returns the changed model.
+    pub fn model(&self) -> &M {
+        match self {
+            Self::Inserted { model, .. }
+            | Self::Updated { model, .. }
+            | Self::Removed { model, .. } => model,
+        }
+    }
+
+    /// Returns the primary key of the changed model.
+    pub fn primary_key(&self) -> &M::PrimaryKey {
+        match self {
+            Self::Inserted { primary_key, .. }
+            | Self::Updated { primary_key, .. }
+            | Self::Removed { primary_key, .. } => primary_key,
+        }
+    }
+}
+
+impl<M> Clone for ModelEvent<M>
+where
+    M: DatabaseModel + Clone,
+    M::PrimaryKey: Clone,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Self::Inserted { model, primary_key } => Self::Inserted {
+                model: model.clone(),
+                primary_key: primary_key.clone(),
+            },
+            Self::Updated { model, primary_key } => Self::Updated {
+                model: model.clone(),
+                primary_key: primary_key.clone(),
+            },
+            Self::Removed { model, primary_key } => Self::Removed {
+                model: model.clone(),
+                primary_key: primary_key.clone(),
+            },
+        }
+    }
+}
+
+impl<M> fmt::Debug for ModelEvent<M>
+where
+    M: DatabaseModel + fmt::Debug,
+    M::PrimaryKey: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, model, primary_key) = match self {
+            Self::Inserted { model, primary_key } => {
+                ("Inserted", model, primary_key)
+            }
+            Self::Updated { model, primary_key } => {
+                ("Updated", model, primary_key)
+            }
+            Self::Removed { model, primary_key } => {
+                ("Removed", model, primary_key)
+            }
+        };
+        f.debug_struct(name)
+            .field("model", model)
+            .field("primary_key", primary_key)
+            .finish()
+    }
+}
+
+/// Implemented for models marked by `events`, required to
+/// [subscribe](crate::DatabaseConnection::subscribe) to their changes.
+pub trait Observable: DatabaseModel + Clone + Send + 'static {}
+
+/// The senders of all subscribers by the type of their model.
+#[derive(Default)]
+pub(crate) struct Subscribers {
+    senders: Mutex<HashMap<TypeId, Vec<Box<dyn Any + Send>>>>,
+}
+
+impl Subscribers {
+    /// Adds a subscriber to the changes of the model `M`.
+    pub(crate) fn subscribe<M>(&self) -> Receiver<ModelEvent<M>>
+    where
+        M: Observable,
+        M::PrimaryKey: Clone + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<ModelEvent<M>>();
+        self.senders
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(TypeId::of::<M>())
+            .or_default()
+            .push(Box::new(sender));
+        receiver
+    }
+
+    /// Sends the event returned by `event` to all subscribers of the model
+    /// `M`. The event is only created if there is a subscriber, those that
+    /// dropped their receiver are removed.
+    pub(crate) fn publish<M, F>(&self, event: F)
+    where
+        M: Observable,
+        M::PrimaryKey: Clone + Send + 'static,
+        F: FnOnce() -> ModelEvent<M>,
+    {
+        let mut senders =
+            self.senders.lock().unwrap_or_else(PoisonError::into_inner);
+        let senders = match senders.get_mut(&TypeId::of::<M>()) {
+            Some(senders) if !senders.is_empty() => senders,
+            _ => return,
+        };
+        let event = event();
+        senders.retain(|sender| {
+            matches!(
+                sender.downcast_ref::<Sender<ModelEvent<M>>>(),
+                Some(sender) if sender.send(event.clone()).is_ok()
+            )
+        });
+    }
+}
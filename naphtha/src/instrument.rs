@@ -0,0 +1,125 @@
+//! Instrumentation of the operations executed by the
+//! [DatabaseConnection](crate::DatabaseConnection). Used by the code generated
+//! by the `#[model]` attribute.
+
+use std::time::Duration;
+#[cfg(feature = "tracing")]
+use tracing_dep::{field::Empty, Span};
+#[cfg(feature = "metrics")]
+use {
+    metrics_dep::{counter, gauge, histogram, Label},
+    std::time::Instant,
+};
+
+/// Creates the span of a [custom](crate::DatabaseConnection::custom) function.
+#[cfg(feature = "tracing")]
+pub(crate) fn custom_span() -> Span {
+    tracing_dep::info_span!(
+        target: "naphtha",
+        "custom",
+        lock_wait_us = Empty,
+        duration_us = Empty,
+    )
+}
+
+/// Records the time waited for the connection lock and the duration of the
+/// query on the current span.
+pub(crate) fn record_timings(lock_wait: Duration, duration: Duration) {
+    #[cfg(feature = "tracing")]
+    {
+        let span = Span::current();
+        span.record("lock_wait_us", lock_wait.as_micros() as u64);
+        span.record("duration_us", duration.as_micros() as u64);
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = (lock_wait, duration);
+}
+
+/// Measures an operation, recording the counter `naphtha_operations_total`
+/// and the histogram `naphtha_operation_duration_seconds` when dropped. The
+/// outcome is `error` unless the operation [succeeded](Self::succeeded).
+#[cfg(feature = "metrics")]
+pub struct Operation {
+    model: Option<&'static str>,
+    operation: &'static str,
+    succeeded: bool,
+    start: Instant,
+}
+
+#[cfg(feature = "metrics")]
+impl Operation {
+    /// Starts measuring the operation of the given model.
+    pub fn start(model: &'static str, operation: &'static str) -> Self {
+        Self {
+            model: Some(model),
+            operation,
+            succeeded: false,
+            start: Instant::now(),
+        }
+    }
+
+    /// Starts measuring a [custom](crate::DatabaseConnection::custom)
+    /// function, which is not related to a model.
+    pub(crate) fn custom() -> Self {
+        Self {
+            model: None,
+            operation: "custom",
+            succeeded: false,
+            start: Instant::now(),
+        }
+    }
+
+    /// Marks the operation as succeeded.
+    pub fn succeeded(&mut self) {
+        self.succeeded = true;
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for Operation {
+    fn drop(&mut self) {
+        let outcome = if self.succeeded { "success" } else { "error" };
+        let mut labels = vec![];
+        if let Some(model) = self.model {
+            labels.push(Label::new("model", model));
+        }
+        labels.push(Label::new("operation", self.operation));
+        labels.push(Label::new("outcome", outcome));
+        counter!("naphtha_operations_total", labels.clone()).increment(1);
+        histogram!("naphtha_operation_duration_seconds", labels)
+            .record(self.start.elapsed());
+    }
+}
+
+/// Tracks a thread waiting for a connection lock in the gauge
+/// `naphtha_lock_waiting`. The time waited is recorded in the histogram
+/// `naphtha_lock_wait_seconds` when dropped.
+pub(crate) struct LockWait {
+    #[cfg(feature = "metrics")]
+    start: Instant,
+}
+
+impl LockWait {
+    pub(crate) fn start() -> Self {
+        #[cfg(feature = "metrics")]
+        gauge!("naphtha_lock_waiting").increment(1.0);
+        Self {
+            #[cfg(feature = "metrics")]
+            start: Instant::now(),
+        }
+    }
+
+    /// Counts a timeout in `naphtha_lock_timeouts_total`.
+    pub(crate) fn timed_out(self) {
+        #[cfg(feature = "metrics")]
+        counter!("naphtha_lock_timeouts_total").increment(1);
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for LockWait {
+    fn drop(&mut self) {
+        gauge!("naphtha_lock_waiting").decrement(1.0);
+        histogram!("naphtha_lock_wait_seconds").record(self.start.elapsed());
+    }
+}
@@ -0,0 +1,165 @@
+//! Dumps and restores the tables of models marked by `serde` as
+//! newline delimited JSON, one model per line.
+//!
+//! The `serde` flag derives [Serialize](crate::serde::Serialize) and
+//! [Deserialize](crate::serde::Deserialize) for the model and implements
+//! [JsonTransfer]. The members are named by their column, members marked by
+//! `#[naphtha(skip)]` and `#[naphtha(snapshot)]` are left out. Members marked
+//! by `#[naphtha(sensitive)]` are exported with their value, as they are
+//! required to restore the rows, so the dumps have to be protected
+//! accordingly. `sensitive` only redacts the `Debug` output and logs.
+//!
+//! ```ignore
+//! #[model(table_name = "persons", primary_key = "id", serde)]
+//! pub struct Person {
+//!     id: i32,
+//!     pub description: String,
+//! }
+//!
+//! let file = std::fs::File::create("persons.ndjson")?;
+//! Person::export_json(&db, std::io::BufWriter::new(file))?;
+//!
+//! let file = std::fs::File::open("persons.ndjson")?;
+//! Person::import_json(&other_db, std::io::BufReader::new(file))?;
+//! ```
+//!
+//! Both are executed on the primary connection within a single transaction
+//! in batches of [BATCH_SIZE] models. The models of a batch are inserted one
+//! by one, as diesel does not insert multiple rows by a single statement on
+//! SQLite. They are not retried on broken connections, because the input
+//! respectively output is consumed partially.
+//!
+//! Imported models are inserted with their primary key as given, without
+//! calling the handlers of [insert](crate::DatabaseModelModifier::insert) nor
+//! recording them in the audit log or history. On PostgreSQL, the sequence of
+//! the primary key of models with `key_strategy = "database"` is set to the
+//! largest imported key within the same transaction, so that subsequent
+//! insertions do not collide with the imported rows.
+
+use {
+    crate::{
+        serde::{de::DeserializeOwned, Serialize},
+        serde_json,
+        ConnectionError,
+        DatabaseConnection,
+    },
+    diesel::result::{Error, QueryResult},
+    std::{
+        cell::{Cell, RefCell},
+        io::{BufRead, Write},
+    },
+};
+
+/// The number of models loaded by a single statement, respectively read
+/// before they are inserted.
+pub const BATCH_SIZE: i64 = 500;
+
+/// Dumps and restores the table of a model, implemented for all models marked
+/// by `serde`.
+pub trait JsonTransfer<T>: Sized {
+    /// Writes all models of the table to `writer`, ordered by their primary
+    /// key. Returns the number of written models.
+    fn export_json<W: Write>(
+        conn: &DatabaseConnection<T>,
+        writer: W,
+    ) -> Result<usize, ConnectionError>;
+
+    /// Inserts all models read from `reader` into the table. Returns the
+    /// number of inserted models, nothing is inserted if one of them fails.
+    fn import_json<R: BufRead>(
+        conn: &DatabaseConnection<T>,
+        reader: R,
+    ) -> Result<usize, ConnectionError>;
+}
+
+/// Advances the sequence of the primary key after an import, implemented for
+/// the connection of every enabled backend.
+#[doc(hidden)]
+pub trait AdvanceSequence {
+    /// Sets the sequence of the given column to the largest value in the
+    /// table. Does nothing on backends without sequences.
+    fn advance_sequence(&self, table: &str, column: &str) -> QueryResult<()>;
+}
+
+/// Writes models as lines of JSON, used by the generated
+/// [export_json](JsonTransfer::export_json).
+#[doc(hidden)]
+pub struct LineWriter<W> {
+    writer: RefCell<W>,
+}
+
+impl<W: Write> LineWriter<W> {
+    /// Creates a writer of lines to the given one.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: RefCell::new(writer),
+        }
+    }
+
+    /// Writes the given model as line.
+    pub fn write<M: Serialize>(&self, model: &M) -> QueryResult<()> {
+        let mut writer = self.writer.borrow_mut();
+        serde_json::to_writer(&mut *writer, model)
+            .and_then(|_| {
+                writer.write_all(b"\n").map_err(serde_json::Error::io)
+            })
+            .map_err(|e| Error::SerializationError(e.into()))
+    }
+
+    /// Flushes the written lines.
+    pub fn flush(&self) -> QueryResult<()> {
+        self.writer
+            .borrow_mut()
+            .flush()
+            .map_err(|e| Error::SerializationError(e.into()))
+    }
+}
+
+/// Reads models from lines of JSON, used by the generated
+/// [import_json](JsonTransfer::import_json).
+#[doc(hidden)]
+pub struct LineReader<R> {
+    reader: RefCell<R>,
+    line: Cell<usize>,
+}
+
+impl<R: BufRead> LineReader<R> {
+    /// Creates a reader of lines from the given one.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: RefCell::new(reader),
+            line: Cell::new(0),
+        }
+    }
+
+    /// Reads the next [BATCH_SIZE] models, empty lines are skipped. Returns
+    /// an empty batch at the end of the input.
+    pub fn read_batch<M: DeserializeOwned>(&self) -> QueryResult<Vec<M>> {
+        let mut reader = self.reader.borrow_mut();
+        let mut batch = vec![];
+        let mut buffer = String::new();
+        while batch.len() < BATCH_SIZE as usize {
+            buffer.clear();
+            let line = self.line.get() + 1;
+            let read = reader.read_line(&mut buffer).map_err(|e| {
+                Error::DeserializationError(
+                    format!("line {}: {}", line, e).into(),
+                )
+            })?;
+            if read == 0 {
+                break;
+            }
+            self.line.set(line);
+            if buffer.trim().is_empty() {
+                continue;
+            }
+            let model = serde_json::from_str(&buffer).map_err(|e| {
+                Error::DeserializationError(
+                    format!("line {}: {}", line, e).into(),
+                )
+            })?;
+            batch.push(model);
+        }
+        Ok(batch)
+    }
+}
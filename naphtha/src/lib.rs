@@ -14,16 +14,29 @@
 //! ## Features overview
 //!
 //! * Most common function implementations `insert`, `update`, `remove` for your
-//! models.
+//!   models.
 //! * Custom transactions provided by the `custom` function
 //! * [DatabaseUpdateHandler](DatabaseUpdateHandler) enables you to change the models values before and
-//! after the `update` transaction to the database.
+//!   after the `update` transaction to the database.
 //! * Change database on specific model in your application without the need to
-//! change your code.
+//!   change your code.
 //! * Possibility to query a model from the database by using one of its member.
 //! * Integrated [barrel] for writing your SQL migrations and the possibility to apply them during
-//! runtime.
+//!   runtime.
 //! * Thread safe handling of the database connection.
+//! * [tracing] spans for all generated operations when using the `tracing`
+//!   feature.
+//! * Operation counters, latency histograms and lock contention gauges using
+//!   the [metrics] facade when using the `metrics` feature.
+//! * An [audit] log of the changes to your models when using the `audit`
+//!   feature.
+//! * A [temporal] history of the previous versions of your models when using
+//!   the `temporal` feature.
+//! * In-process [events] about the committed changes to your models.
+//! * Partial updates of the changed members of a model, see [dirty].
+//! * Dumping and restoring tables as newline delimited [json] when using the
+//!   `serde` feature.
+//! * Row level [changes] of SQLite tables, including raw SQL writes.
 //!
 //! ## Supported databases
 //!
@@ -46,6 +59,18 @@
 //! // do some database work
 //! ```
 //!
+//! ### Selecting the database at runtime
+//!
+//! The [AnyConnection] selects the backend by the scheme of the database URL,
+//! e.g. `sqlite://`, `mysql://` or `postgres://`, out of the enabled features.
+//! All models can be used with it, so one binary supports all of them.
+//!
+//! ```rust
+//! use naphtha::{AnyConnection, DatabaseConnection, DatabaseConnect};
+//! let url = std::env::var("DATABASE_URL").unwrap_or(":memory:".into());
+//! let db: DatabaseConnection<AnyConnection> = DatabaseConnection::connect(&url).unwrap();
+//! ```
+//!
 //! ### Defining a model and use database connection
 //!
 //! To create a model and its database integration, the following code is required.
@@ -137,15 +162,133 @@
 
 pub use diesel;
 pub extern crate anyhow;
+pub extern crate chrono;
 pub extern crate log;
+#[cfg(feature = "metrics")]
+pub use metrics_dep as metrics;
+#[cfg(any(feature = "audit", feature = "serde"))]
+pub use serde_dep as serde;
+#[cfg(any(feature = "audit", feature = "serde"))]
+pub use serde_json_dep as serde_json;
+#[cfg(feature = "tracing")]
+pub use tracing_dep as tracing;
+#[cfg(feature = "uuid")]
+pub use uuid_dep as uuid;
+
+// Makes the code generated by the model macro available to the tests.
+#[cfg(test)]
+extern crate self as naphtha;
+#[cfg(any(test, feature = "audit", feature = "sqlite"))]
+#[macro_use]
+extern crate diesel as _;
 
-use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use {
+    diesel::result::QueryResult,
+    std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+            LockResult,
+            Mutex,
+            MutexGuard,
+            PoisonError,
+            TryLockError,
+        },
+        time::{Duration, Instant},
+    },
+};
 
 /// Defines your `struct` as a model and implements the required traits for
 /// interacting with the database. Currently only *named* `struct` member are
 /// supported.
+///
+/// The `primary_key` parameter takes a comma separated list of members for
+/// composite primary keys, e.g. `primary_key = "tenant_id, user_id"`. The
+/// [PrimaryKey](DatabaseModel::PrimaryKey) is then a tuple of the member
+/// types in the same order.
+///
+/// The `key_strategy` parameter defines who assigns the primary key:
+///
+/// * `"database"` (default) the key is assigned by the database on
+///   [insert](DatabaseModelModifier::insert) and read back into the model. The
+///   model must contain the [default_primary_key](DatabaseModel::default_primary_key)
+///   before insertion.
+/// * `"client"` the key is assigned by your application, e.g. a UUID, and
+///   inserted as given. This is the only strategy available for composite keys.
+///   UUID keys require the `uuid` feature. On PostgreSQL use `uuid::Uuid` as
+///   member and `diesel::sql_types::Uuid` in your schema, on SQLite and MySQL
+///   use `naphtha::types::Uuid` and `naphtha::sql_types::Uuid` instead.
+///
+/// The `schema` parameter takes the path to the module containing the
+/// `table!` definition, e.g. `schema = "crate::db::schema"`. It defaults to
+/// `self::schema`, a child module named `schema`. The generated code uses
+/// fully qualified paths and does not import anything into your module.
+///
+/// The `derive` parameter takes a comma separated list of traits that are
+/// derived for the model and replaces the default `derive = "Debug"`. The
+/// model is still required to implement [Debug](std::fmt::Debug), so you can
+/// provide your own implementation, e.g. to hide secrets. Additional derives
+/// can also be added by a regular `#[derive(...)]` attribute.
+///
+/// The `log` parameter defines how the model is described in the error
+/// messages of [insert](DatabaseModelModifier::insert),
+/// [update](DatabaseModelModifier::update) and
+/// [remove](DatabaseModelModifier::remove):
+///
+/// * `"model"` (default) the whole model is printed by its
+///   [Debug](std::fmt::Debug) representation.
+/// * `"primary_key"` only the primary key is printed. The model is not
+///   required to implement [Debug](std::fmt::Debug) in this case.
+///
+/// The `audited` flag, e.g. `#[model(table_name = "persons", primary_key =
+/// "id", audited)]`, records all changes of the model in the audit log
+/// (requires the `audit` feature), see the `audit` module.
+///
+/// The `events` flag publishes the committed changes of the model to the
+/// receivers returned by [subscribe](DatabaseConnection::subscribe), see the
+/// [events] module. The model is required to implement [Clone].
+///
+/// The `reload_on_insert` flag [reloads](DatabaseModelModifier::reload) the
+/// model after its insertion, populating the values computed by the
+/// database, e.g. column defaults or triggers. If reloading fails, `insert`
+/// returns the error although the model has been inserted.
+///
+/// The `serde` flag derives [Serialize](serde::Serialize) and
+/// [Deserialize](serde::Deserialize) for the model, respecting the member
+/// attributes below, and implements the NDJSON export and import of the
+/// [json] module (requires the `serde` feature).
+///
+/// The `temporal` flag keeps every previous version of the model in the table
+/// `<table_name>_history` (requires the `temporal` feature), see the
+/// `temporal` module. The column names `history_id`, `valid_from` and
+/// `valid_to` are reserved for it.
+///
+/// The `query_by_*` functions are defined by a trait named after the model,
+/// e.g. `PersonQueries` for `Person`, that is generated next to it. Therefore
+/// multiple models can be defined in the same module. The trait has to be in
+/// scope when querying from another module.
+///
+/// Parents of the model are given by `#[belongs_to(Parent)]` or
+/// `#[belongs_to(Parent, foreign_key = "parent_id")]`, like the diesel
+/// `Associations` derive.
+///
+/// The members can be configured by the `#[naphtha(...)]` attribute:
+///
+/// * `column = "name"` maps the member to the column `name` on the table.
+/// * `skip` excludes the member from the database. It is populated by its
+///   [Default] value when loaded.
+/// * `sensitive` prints `<redacted>` instead of the value in the derived
+///   [Debug](std::fmt::Debug) implementation and therefore in all error
+///   messages, e.g. for password hashes or personal data. The value is still
+///   exported by the `serde` flag.
+/// * `snapshot` marks a member of type [Snapshot](dirty::Snapshot) that
+///   tracks the changes to the model, so that
+///   [update](DatabaseModelModifier::update) only sets the changed columns,
+///   see the [dirty] module.
 pub use naphtha_proc_macro::model;
 
+#[cfg(feature = "audit")]
+pub mod audit;
 #[cfg(any(
     feature = "barrel-sqlite",
     feature = "barrel-mysql",
@@ -153,34 +296,551 @@ pub use naphtha_proc_macro::model;
 ))]
 /// Re-exports the [barrel] crate including small trait additions required by naphtha.
 pub mod barrel;
+#[cfg(feature = "sqlite")]
+pub mod changes;
+pub mod config;
 mod database_impl;
+pub mod dirty;
+mod error;
+pub mod events;
+#[doc(hidden)]
+pub mod instrument;
+#[cfg(feature = "serde")]
+pub mod json;
+mod poison;
+pub mod retry;
+mod slow_query;
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "pg"))]
+pub use database_impl::AnyConnection;
+pub use {
+    config::DatabaseConfig,
+    error::ConnectionError,
+    poison::PoisonPolicy,
+    retry::RetryPolicy,
+    slow_query::SlowQueryLog,
+};
+#[cfg(all(feature = "uuid", any(feature = "sqlite", feature = "mysql")))]
+pub mod sql_types;
+#[cfg(feature = "temporal")]
+pub mod temporal;
 mod tests;
+#[cfg(all(feature = "uuid", any(feature = "sqlite", feature = "mysql")))]
+pub mod types;
+
+/// Establishes a new connection, used to replace a broken one.
+type Connector<T> = Arc<dyn Fn() -> anyhow::Result<T> + Send + Sync>;
+
+/// A connection with the function to re-establish it.
+struct Handle<T> {
+    connection: Arc<Mutex<T>>,
+    connector: Option<Connector<T>>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            connection: Arc::clone(&self.connection),
+            connector: self.connector.clone(),
+        }
+    }
+}
+
+impl<T> Handle<T> {
+    /// Replaces the connection by a newly established one.
+    fn reconnect(&self) -> anyhow::Result<()> {
+        let connector = self.connector.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("The connection does not support reconnecting!")
+        })?;
+        let connection = connector()?;
+        let mut c = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *c = connection;
+        Ok(())
+    }
+}
 
 /// Thin wrapper around a [Connection](diesel::Connection).
-pub struct DatabaseConnection<T>(Arc<Mutex<T>>);
+///
+/// Optionally, read replicas can be attached using
+/// [with_replica](DatabaseConnection::with_replica). Reads executed by
+/// [read](DatabaseConnection::read), including the generated `query_by_*`
+/// functions, are then distributed round robin to the replicas. Everything
+/// else, like `insert`, `update`, `remove` and [custom](DatabaseConnection::custom),
+/// is always executed on the primary connection.
+///
+/// Connections established by [DatabaseConnect] are re-established when they
+/// break, see the [retry] module.
+///
+/// By default, waiting for the lock of the connection blocks until it is
+/// released. Use [with_lock_timeout](DatabaseConnection::with_lock_timeout)
+/// to limit the waiting time of the generated model operations, they return
+/// [ConnectionError::LockTimeout] afterwards.
+///
+/// A poisoned lock is handled as defined by the [PoisonPolicy], see
+/// [with_poison_policy](DatabaseConnection::with_poison_policy).
+///
+/// Slow statements of the generated model operations can be logged by a
+/// [SlowQueryLog], see
+/// [with_slow_query_log](DatabaseConnection::with_slow_query_log).
+///
+/// The actor performing the changes, e.g. the logged in user, is set by
+/// [set_actor](DatabaseConnection::set_actor) and recorded by the audit log.
+///
+/// The committed changes to models marked by `events` are received by
+/// [subscribe](DatabaseConnection::subscribe).
+///
+/// All changes to the rows of a SQLite table are received by
+/// [watch_table](DatabaseConnection::watch_table).
+pub struct DatabaseConnection<T> {
+    primary: Handle<T>,
+    replicas: Vec<Handle<T>>,
+    next_replica: Arc<AtomicUsize>,
+    retry_policy: RetryPolicy,
+    lock_timeout: Option<Duration>,
+    poison_policy: PoisonPolicy,
+    slow_query_log: Option<SlowQueryLog>,
+    actor: Arc<Mutex<Option<String>>>,
+    subscribers: Arc<events::Subscribers>,
+    #[cfg(feature = "sqlite")]
+    row_watchers: Arc<changes::RowWatchers>,
+    validate: fn(&T) -> QueryResult<()>,
+}
 
 impl<T> DatabaseConnection<T> {
-    /// Aquires a lock to the wrapped connection.
-    pub fn lock(
+    pub(crate) fn new(connection: Arc<Mutex<T>>) -> Self
+    where
+        T: DatabaseHealthCheck,
+    {
+        Self {
+            primary: Handle {
+                connection,
+                connector: None,
+            },
+            replicas: vec![],
+            next_replica: Arc::new(AtomicUsize::new(0)),
+            retry_policy: RetryPolicy::default(),
+            lock_timeout: None,
+            poison_policy: PoisonPolicy::default(),
+            slow_query_log: None,
+            actor: Arc::new(Mutex::new(None)),
+            subscribers: Arc::default(),
+            #[cfg(feature = "sqlite")]
+            row_watchers: Arc::default(),
+            validate: |c| {
+                c.rollback_transactions()?;
+                c.ping()
+            },
+        }
+    }
+
+    /// Sets the function that establishes a new connection to replace the
+    /// primary connection when it broke.
+    pub fn with_connector<F>(mut self, connector: F) -> Self
+    where
+        F: Fn() -> anyhow::Result<T> + Send + Sync + 'static,
+    {
+        self.primary.connector = Some(Arc::new(connector));
+        self
+    }
+
+    /// Sets the policy for retrying idempotent operations on broken
+    /// connections.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets the maximum time to wait for the lock of the connection, used by
+    /// [read](DatabaseConnection::read), [write](DatabaseConnection::write)
+    /// and therefore all generated model operations, as well as by
+    /// [lock](DatabaseConnection::lock) and
+    /// [try_custom](DatabaseConnection::try_custom).
+    /// [custom](DatabaseConnection::custom) waits without a timeout.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the policy for handling a poisoned lock.
+    pub fn with_poison_policy(mut self, poison_policy: PoisonPolicy) -> Self {
+        self.poison_policy = poison_policy;
+        self
+    }
+
+    /// Enables logging of the statements executed by the generated model
+    /// operations that exceed the threshold of the given log.
+    pub fn with_slow_query_log(mut self, slow_query_log: SlowQueryLog) -> Self {
+        self.slow_query_log = Some(slow_query_log);
+        self
+    }
+
+    /// Returns the slow query log, if enabled.
+    pub fn slow_query_log(&self) -> Option<&SlowQueryLog> {
+        self.slow_query_log.as_ref()
+    }
+
+    /// Sets the actor performing the following changes on this connection,
+    /// e.g. the id of the logged in user. It is shared with the connections
+    /// returned by [primary](DatabaseConnection::primary).
+    pub fn set_actor(&self, actor: Option<&str>) {
+        *self.actor.lock().unwrap_or_else(PoisonError::into_inner) =
+            actor.map(str::to_string);
+    }
+
+    /// Returns the actor set by [set_actor](DatabaseConnection::set_actor).
+    pub fn actor(&self) -> Option<String> {
+        self.actor
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns a receiver of the committed changes to the model `M` made by
+    /// the generated operations on this connection and the connections
+    /// returned by [primary](DatabaseConnection::primary). The subscription
+    /// ends when the receiver is dropped.
+    pub fn subscribe<M>(
         &self,
-    ) -> Result<MutexGuard<'_, T>, PoisonError<MutexGuard<'_, T>>> {
-        self.0.lock()
+    ) -> std::sync::mpsc::Receiver<events::ModelEvent<M>>
+    where
+        M: events::Observable,
+        M::PrimaryKey: Clone + Send + 'static,
+    {
+        self.subscribers.subscribe()
     }
 
-    /// Executes the custom function to the database instance.
+    /// Sends the event returned by `event` to all subscribers of the model
+    /// `M`, it is only called if there is one.
+    #[doc(hidden)]
+    pub fn publish<M, F>(&self, event: F)
+    where
+        M: events::Observable,
+        M::PrimaryKey: Clone + Send + 'static,
+        F: FnOnce() -> events::ModelEvent<M>,
+    {
+        self.subscribers.publish(event)
+    }
+
+    /// Adds the given connection as read replica.
+    ///
+    /// Replicas of the given connection are ignored, only its primary
+    /// connection is added.
+    pub fn with_replica(mut self, replica: DatabaseConnection<T>) -> Self {
+        self.replicas.push(replica.primary);
+        self
+    }
+
+    /// Returns the number of attached read replicas.
+    pub fn replica_count(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// Returns a connection that shares the primary connection but has no
+    /// replicas attached, so all reads are executed on the primary.
+    ///
+    /// Use this to read your own writes when the replicas may lag behind.
+    pub fn primary(&self) -> DatabaseConnection<T> {
+        Self {
+            primary: self.primary.clone(),
+            replicas: vec![],
+            next_replica: Arc::new(AtomicUsize::new(0)),
+            retry_policy: self.retry_policy,
+            lock_timeout: self.lock_timeout,
+            poison_policy: self.poison_policy,
+            slow_query_log: self.slow_query_log,
+            actor: Arc::clone(&self.actor),
+            subscribers: Arc::clone(&self.subscribers),
+            #[cfg(feature = "sqlite")]
+            row_watchers: Arc::clone(&self.row_watchers),
+            validate: self.validate,
+        }
+    }
+
+    /// Re-establishes the primary connection and all replicas.
+    pub fn reconnect(&self) -> anyhow::Result<()> {
+        for handle in std::iter::once(&self.primary).chain(&self.replicas) {
+            handle.reconnect()?;
+        }
+        Ok(())
+    }
+
+    /// Aquires a lock to the wrapped primary connection, waiting at most the
+    /// time set by [with_lock_timeout](Self::with_lock_timeout). A poisoned
+    /// lock is handled as defined by the [PoisonPolicy].
+    pub fn lock(&self) -> Result<MutexGuard<'_, T>, ConnectionError> {
+        self.acquire(&self.primary, self.lock_timeout)
+    }
+
+    /// Aquires a lock to the wrapped primary connection, waiting at most the
+    /// given time. Returns [ConnectionError::LockTimeout] if the connection
+    /// is still locked afterwards. A poisoned lock is handled as defined by
+    /// the [PoisonPolicy].
+    pub fn lock_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<MutexGuard<'_, T>, ConnectionError> {
+        self.acquire(&self.primary, Some(timeout))
+    }
+
+    /// Aquires a lock to the wrapped primary connection without waiting.
+    /// Returns [ConnectionError::LockTimeout] if it is currently locked.
+    pub fn try_lock(&self) -> Result<MutexGuard<'_, T>, ConnectionError> {
+        self.acquire(&self.primary, Some(Duration::ZERO))
+    }
+
+    /// Executes the custom function to the primary database instance. Waits
+    /// for the lock of the connection without a timeout, use
+    /// [try_custom](Self::try_custom) to limit the waiting.
+    ///
+    /// # Panics
+    ///
+    /// If the lock of the connection is poisoned and cannot be recovered as
+    /// defined by the [PoisonPolicy].
+    #[track_caller]
     pub fn custom<R, F>(&self, query: F) -> R
     where
         F: Fn(&T) -> R,
     {
-        let c = self.0.lock().expect("Could not aquire connection lock!");
-        query(&*c)
+        self.run_custom(query, None)
+            .expect("Could not aquire connection lock!")
+    }
+
+    /// Executes the custom function to the primary database instance. Waits
+    /// at most the time set by [with_lock_timeout](Self::with_lock_timeout)
+    /// for the lock of the connection, which is handled as defined by the
+    /// [PoisonPolicy] if it is poisoned.
+    pub fn try_custom<R, F>(&self, query: F) -> Result<R, ConnectionError>
+    where
+        F: Fn(&T) -> R,
+    {
+        self.run_custom(query, self.lock_timeout)
+    }
+
+    fn run_custom<R, F>(
+        &self,
+        query: F,
+        timeout: Option<Duration>,
+    ) -> Result<R, ConnectionError>
+    where
+        F: Fn(&T) -> R,
+    {
+        #[cfg(feature = "tracing")]
+        let span = instrument::custom_span();
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+        #[cfg(feature = "metrics")]
+        let mut operation = instrument::Operation::custom();
+        let lock_start = Instant::now();
+        let c = self.acquire(&self.primary, timeout)?;
+        let lock_wait = lock_start.elapsed();
+        let start = Instant::now();
+        let result = query(&*c);
+        instrument::record_timings(lock_wait, start.elapsed());
+        #[cfg(feature = "metrics")]
+        operation.succeeded();
+        Ok(result)
+    }
+
+    /// Executes the read only query to the next replica, or to the primary
+    /// database instance if no replicas are attached. The query is retried on
+    /// broken connections.
+    pub fn read<R, F>(&self, query: F) -> Result<R, ConnectionError>
+    where
+        F: Fn(&T) -> QueryResult<R>,
+    {
+        let handle = if self.replicas.is_empty() {
+            &self.primary
+        } else {
+            let next = self.next_replica.fetch_add(1, Ordering::Relaxed);
+            &self.replicas[next % self.replicas.len()]
+        };
+        self.execute(handle, self.retry_policy.max_retries(), query)
+    }
+
+    /// Executes the operation to the primary database instance. A broken
+    /// connection is re-established, but the operation is not retried.
+    pub fn write<R, F>(&self, operation: F) -> Result<R, ConnectionError>
+    where
+        F: Fn(&T) -> QueryResult<R>,
+    {
+        self.execute(&self.primary, 0, operation)
+    }
+
+    /// Executes the idempotent operation to the primary database instance.
+    /// The operation is retried on broken connections.
+    pub fn write_idempotent<R, F>(
+        &self,
+        operation: F,
+    ) -> Result<R, ConnectionError>
+    where
+        F: Fn(&T) -> QueryResult<R>,
+    {
+        self.execute(&self.primary, self.retry_policy.max_retries(), operation)
+    }
+
+    fn execute<R, F>(
+        &self,
+        handle: &Handle<T>,
+        max_retries: u32,
+        operation: F,
+    ) -> Result<R, ConnectionError>
+    where
+        F: Fn(&T) -> QueryResult<R>,
+    {
+        let mut retry = 0;
+        loop {
+            let result = {
+                let lock_start = Instant::now();
+                let c = self.acquire(handle, self.lock_timeout)?;
+                let lock_wait = lock_start.elapsed();
+                let start = Instant::now();
+                let result = operation(&*c);
+                instrument::record_timings(lock_wait, start.elapsed());
+                result
+            };
+            match result {
+                Err(e)
+                    if handle.connector.is_some()
+                        && retry::is_connection_error(&e) =>
+                {
+                    if retry >= max_retries {
+                        if let Err(msg) = handle.reconnect() {
+                            log::warn!("Could not reconnect: {}", msg);
+                        }
+                        return Err(ConnectionError::Query(e));
+                    }
+                    std::thread::sleep(self.retry_policy.backoff(retry));
+                    retry += 1;
+                    log::warn!("Reconnecting after error: {}", e);
+                    if let Err(msg) = handle.reconnect() {
+                        log::warn!("Could not reconnect: {}", msg);
+                    }
+                }
+                result => return result.map_err(ConnectionError::Query),
+            }
+        }
+    }
+}
+
+impl<T> DatabaseConnection<T> {
+    /// Locks the connection of the handle, waiting at most the given time.
+    fn acquire<'a>(
+        &self,
+        handle: &'a Handle<T>,
+        timeout: Option<Duration>,
+    ) -> Result<MutexGuard<'a, T>, ConnectionError> {
+        let poisoned = match lock(&handle.connection, timeout)? {
+            Ok(guard) => return Ok(guard),
+            Err(poisoned) => poisoned,
+        };
+        let message = poisoned.to_string();
+        let mut guard = poisoned.into_inner();
+        let recovered = match self.poison_policy {
+            PoisonPolicy::Fail => return Err(ConnectionError::Lock(message)),
+            PoisonPolicy::Recover => (self.validate)(&*guard).map_err(|e| {
+                format!("Validating the connection failed: {}", e)
+            }),
+            PoisonPolicy::Reconnect => match &handle.connector {
+                Some(connector) => connector()
+                    .map(|connection| *guard = connection)
+                    .map_err(|e| format!("Reconnecting failed: {}", e)),
+                None => {
+                    Err("The connection does not support reconnecting!"
+                        .to_string())
+                }
+            },
+        };
+        match recovered {
+            Ok(()) => {
+                log::warn!(
+                    "Recovered poisoned connection lock using policy '{}'",
+                    self.poison_policy
+                );
+                handle.connection.clear_poison();
+                Ok(guard)
+            }
+            Err(e) => {
+                log::error!(
+                    "Could not recover poisoned connection lock: {}",
+                    e
+                );
+                Err(ConnectionError::Lock(format!("{}, {}", message, e)))
+            }
+        }
+    }
+}
+
+impl<T: DatabaseHealthCheck> DatabaseConnection<T> {
+    /// Checks the primary connection and all replicas. Broken connections
+    /// are re-established as defined by the [RetryPolicy].
+    pub fn health_check(&self) -> anyhow::Result<()> {
+        for handle in std::iter::once(&self.primary).chain(&self.replicas) {
+            self.execute(handle, self.retry_policy.max_retries(), |c| {
+                c.ping()
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Locks the mutex, waiting at most the given time.
+fn lock<T>(
+    mutex: &Mutex<T>,
+    timeout: Option<Duration>,
+) -> Result<LockResult<MutexGuard<'_, T>>, ConnectionError> {
+    let wait = instrument::LockWait::start();
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return Ok(mutex.lock()),
+    };
+    // std::sync::Mutex does not support waiting with a timeout, so it is
+    // polled with an increasing interval
+    let start = Instant::now();
+    let mut interval = Duration::from_micros(10);
+    loop {
+        match mutex.try_lock() {
+            Ok(guard) => return Ok(Ok(guard)),
+            Err(TryLockError::Poisoned(e)) => return Ok(Err(e)),
+            Err(TryLockError::WouldBlock) => {
+                let elapsed = start.elapsed();
+                if elapsed >= timeout {
+                    wait.timed_out();
+                    return Err(ConnectionError::LockTimeout(timeout));
+                }
+                std::thread::sleep(interval.min(timeout - elapsed));
+                interval = (interval * 2).min(Duration::from_millis(10));
+            }
+        }
     }
 }
 
+/// Checks if a connection to the database is alive.
+pub trait DatabaseHealthCheck {
+    /// Executes a trivial query on the connection.
+    fn ping(&self) -> QueryResult<()>;
+
+    /// Rolls back all open transactions, e.g. left behind by a panic.
+    fn rollback_transactions(&self) -> QueryResult<()>;
+}
+
 /// Contains functions database connection handling.
 pub trait DatabaseConnect<T> {
     /// Establishes a new connection to the given database string.
     fn connect(database_url: &str) -> anyhow::Result<DatabaseConnection<T>>;
+
+    /// Establishes a new connection using the given configuration.
+    fn connect_config(
+        config: &DatabaseConfig,
+    ) -> anyhow::Result<DatabaseConnection<T>> {
+        config.check_pool_size()?;
+        let mut connection = Self::connect(&config.database_url()?)?;
+        for url in config.replica_urls()? {
+            connection = connection.with_replica(Self::connect(&url)?);
+        }
+        Ok(config.configure(connection))
+    }
 }
 
 /// Defines the relation of the model to the database.
@@ -213,6 +873,20 @@ where
     /// Updates `self` on the given database.
     /// *Updates the `updated_at` member if available before updating the database.*.
     fn update(&mut self, conn: &DatabaseConnection<T>) -> ::anyhow::Result<()>;
+    /// Replaces the persisted members of `self` by the row with the same
+    /// primary key, read from the primary database instance. Members marked
+    /// by `#[naphtha(skip)]` are kept. Fails with
+    /// [NotFound](diesel::result::Error::NotFound) if the row has been
+    /// removed.
+    ///
+    /// Implemented by the [model] attribute. The default implementation of
+    /// manually implemented modifiers returns an error.
+    #[allow(unused_variables)]
+    fn reload(&mut self, conn: &DatabaseConnection<T>) -> ::anyhow::Result<()> {
+        Err(anyhow::anyhow!(
+            "DatabaseModelModifier::reload is not implemented for this model!"
+        ))
+    }
 }
 
 /// Methods that are called before and after the transaction executed when
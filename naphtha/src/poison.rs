@@ -0,0 +1,46 @@
+//! Handling of connections whose lock has been poisoned.
+
+use std::{fmt, str::FromStr};
+
+/// Defines how a [DatabaseConnection](crate::DatabaseConnection) handles a
+/// poisoned lock, caused by a panic while the connection has been locked,
+/// e.g. in a [custom](crate::DatabaseConnection::custom) function.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PoisonPolicy {
+    /// Every operation fails with [ConnectionError::Lock](crate::ConnectionError::Lock).
+    #[default]
+    Fail,
+    /// Rolls back all open transactions and checks the connection using
+    /// [DatabaseHealthCheck](crate::DatabaseHealthCheck). The connection is
+    /// used again if it is healthy.
+    Recover,
+    /// Replaces the connection by a newly established one. Requires a
+    /// connection created by [DatabaseConnect](crate::DatabaseConnect).
+    Reconnect,
+}
+
+impl FromStr for PoisonPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "fail" => Ok(PoisonPolicy::Fail),
+            "recover" => Ok(PoisonPolicy::Recover),
+            "reconnect" => Ok(PoisonPolicy::Reconnect),
+            _ => Err(format!(
+                "Unknown poison policy '{}'! Possible values are: fail, recover, reconnect",
+                value
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PoisonPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoisonPolicy::Fail => write!(f, "fail"),
+            PoisonPolicy::Recover => write!(f, "recover"),
+            PoisonPolicy::Reconnect => write!(f, "reconnect"),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Reconnection and retry of operations on broken connections.
+//!
+//! When an operation fails because the connection to the database server
+//! broke, e.g. after a server restart, the [DatabaseConnection](crate::DatabaseConnection)
+//! re-establishes the connection. Idempotent operations like the generated
+//! `query_by_*` functions and `remove` are then retried as defined by the
+//! [RetryPolicy] of the connection. All other operations return the error,
+//! the next call uses the new connection.
+
+use {
+    diesel::result::{DatabaseErrorKind, Error},
+    std::time::Duration,
+};
+
+/// Parts of the error messages of MySQL and PostgreSQL indicating a broken
+/// connection, in lower case.
+const CONNECTION_ERRORS: &[&str] = &[
+    "server has gone away",
+    "lost connection",
+    "server closed the connection",
+    "terminating connection",
+    "no connection to the server",
+    "could not receive data from server",
+    "could not send data to server",
+    "connection refused",
+    "connection reset",
+    "broken pipe",
+];
+
+/// Returns `true` if the error is caused by a broken connection to the
+/// database server.
+pub fn is_connection_error(error: &Error) -> bool {
+    match error {
+        Error::DatabaseError(DatabaseErrorKind::UnableToSendCommand, _) => true,
+        Error::DatabaseError(_, info) => {
+            let message = info.message().to_lowercase();
+            CONNECTION_ERRORS.iter().any(|m| message.contains(m))
+        }
+        _ => false,
+    }
+}
+
+/// Defines how often and with which delay idempotent operations are retried
+/// on broken connections. The delay doubles with every retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Creates a policy retrying the operation at most `max_retries` times.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a policy that never retries. Broken connections are still
+    /// re-established for the next operation.
+    pub fn never() -> Self {
+        Self::new(0)
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum delay between two retries.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Returns the maximum number of retries.
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Returns the delay before the given retry, starting at `0`.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(2u32.saturating_pow(retry))
+            .map_or(self.max_backoff, |b| b.min(self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Retries three times, starting with a delay of 100 milliseconds up to
+    /// five seconds.
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Logging of statements exceeding a duration threshold.
+
+use {
+    diesel::{
+        backend::Backend,
+        query_builder::{QueryBuilder, QueryFragment},
+        result::QueryResult,
+        Connection,
+    },
+    std::{
+        panic::Location,
+        time::{Duration, Instant},
+    },
+};
+
+/// Logs the statements of the generated model operations that take longer
+/// than the threshold, see
+/// [with_slow_query_log](crate::DatabaseConnection::with_slow_query_log).
+///
+/// The statements are logged as warning with the target
+/// `naphtha::slow_query`, containing the SQL, the number of bind parameters,
+/// the duration, the model and the call site. The values of the bind
+/// parameters are redacted unless enabled by
+/// [with_bind_values](SlowQueryLog::with_bind_values).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowQueryLog {
+    threshold: Duration,
+    bind_values: bool,
+}
+
+impl SlowQueryLog {
+    /// Creates a log for statements taking at least the given time.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            bind_values: false,
+        }
+    }
+
+    /// Includes the values of the bind parameters in the log. They may
+    /// contain sensitive data.
+    pub fn with_bind_values(mut self) -> Self {
+        self.bind_values = true;
+        self
+    }
+
+    /// Returns the threshold of the log.
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Executes the statement built by `query` on the connection `c` by
+    /// calling `run`. The statement is logged if it exceeds the threshold of
+    /// the given log, it is built again for rendering the SQL in this case.
+    #[doc(hidden)]
+    pub fn run<C, Q, B, R, F>(
+        log: Option<&Self>,
+        model: &str,
+        location: &Location<'_>,
+        c: &C,
+        query: B,
+        run: F,
+    ) -> QueryResult<R>
+    where
+        C: CountBinds,
+        <C::Backend as Backend>::QueryBuilder: Default,
+        Q: QueryFragment<C::Backend>,
+        B: Fn() -> Q,
+        F: FnOnce(Q) -> QueryResult<R>,
+    {
+        let log = match log {
+            Some(log) => log,
+            None => return run(query()),
+        };
+        let start = Instant::now();
+        let result = run(query());
+        let duration = start.elapsed();
+        if duration >= log.threshold {
+            let (sql, count, binds) = render(c, &query(), log.bind_values);
+            log::warn!(
+                target: "naphtha::slow_query",
+                "Slow query of model {} at {} took {:?}: {} -- {} binds: {}",
+                model,
+                location,
+                duration,
+                sql,
+                count,
+                binds
+            );
+        }
+        result
+    }
+}
+
+/// Counts the bind parameters of queries, implemented for the connection of
+/// every enabled backend.
+#[doc(hidden)]
+pub trait CountBinds: Connection {
+    /// Returns the number of bind parameters of the query.
+    fn count_binds<Q>(&self, query: &Q) -> QueryResult<usize>
+    where
+        Q: QueryFragment<Self::Backend>;
+}
+
+/// Renders the SQL of the query, the number of its bind parameters and their
+/// values if requested.
+fn render<C, Q>(c: &C, query: &Q, bind_values: bool) -> (String, usize, String)
+where
+    C: CountBinds,
+    <C::Backend as Backend>::QueryBuilder: Default,
+    Q: QueryFragment<C::Backend>,
+{
+    let mut builder = <C::Backend as Backend>::QueryBuilder::default();
+    let sql = match query.to_sql(&mut builder) {
+        Ok(()) => builder.finish(),
+        Err(e) => return (format!("<{}>", e), 0, "[]".to_string()),
+    };
+    let count = match c.count_binds(query) {
+        Ok(count) => count,
+        Err(e) => return (format!("<{}>", e), 0, "[]".to_string()),
+    };
+    let binds = match bind_values {
+        true => {
+            let display =
+                diesel::debug_query::<C::Backend, _>(query).to_string();
+            display
+                .strip_prefix(sql.as_str())
+                .and_then(|binds| binds.strip_prefix(" -- binds: "))
+                .unwrap_or("[]")
+                .to_string()
+        }
+        false => "[redacted]".to_string(),
+    };
+    (sql, count, binds)
+}
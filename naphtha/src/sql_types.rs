@@ -0,0 +1,56 @@
+//! Additional SQL types that are supported on SQLite and MySQL.
+//!
+//! Use them in your `table!` definition by importing them, e.g.:
+//!
+//! ```ignore
+//! table! {
+//!     use naphtha::diesel::sql_types::*;
+//!     use naphtha::sql_types::Uuid;
+//!
+//!     persons (id) {
+//!         id -> Uuid,
+//!         description -> Text,
+//!     }
+//! }
+//! ```
+
+use diesel::{
+    query_builder::QueryId,
+    sql_types::{HasSqlType, NotNull, SingleValue},
+};
+
+/// The SQL type for [Uuid](crate::types::Uuid) values.
+///
+/// It is stored as its hyphenated text representation, therefore the column
+/// should be of type `TEXT` or `CHAR(36)`. Use `diesel::sql_types::Uuid` on
+/// PostgreSQL, which stores `uuid::Uuid` as native `UUID`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Uuid;
+
+// The following implementations are equal to the ones derived by `QueryId`
+// and `SqlType` of diesel 1.4, which places them in a function body and
+// therefore triggers the `non_local_definitions` lint.
+
+impl QueryId for Uuid {
+    type QueryId = Self;
+
+    const HAS_STATIC_QUERY_ID: bool = true;
+}
+
+impl NotNull for Uuid {}
+
+impl SingleValue for Uuid {}
+
+#[cfg(feature = "sqlite")]
+impl HasSqlType<Uuid> for diesel::sqlite::Sqlite {
+    fn metadata(_: &()) -> diesel::sqlite::SqliteType {
+        diesel::sqlite::SqliteType::Text
+    }
+}
+
+#[cfg(feature = "mysql")]
+impl HasSqlType<Uuid> for diesel::mysql::Mysql {
+    fn metadata(_: &()) -> diesel::mysql::MysqlType {
+        diesel::mysql::MysqlType::String
+    }
+}
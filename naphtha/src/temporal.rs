@@ -0,0 +1,133 @@
+//! Keeps the previous versions of models marked by `temporal`.
+//!
+//! Before a `temporal` model is updated or removed, its current row is
+//! copied to the history table `<table_name>_history` within the same
+//! transaction. Every copied version is valid from the time it was written
+//! until the time it got replaced. The table consists of the following
+//! columns and is created by the generated migrations when using one of the
+//! `barrel-XXX` features:
+//!
+//! | Column       | Type                  | Content                                  |
+//! |--------------|-----------------------|------------------------------------------|
+//! | `history_id` | primary               | Increasing with every archived version.  |
+//! | ...          | as on the model table | The persisted members of the model.      |
+//! | `valid_from` | nullable `TIMESTAMP`  | Time the version was written.            |
+//! | `valid_to`   | `TIMESTAMP`           | Time the version got replaced.           |
+//!
+//! The insertion of a model is recorded as a version with the same
+//! `valid_from` and `valid_to`, which has never been valid and is not
+//! returned by `history`. Its time becomes `valid_from` of the first version
+//! archived by an update, and the model is not found by `as_of` queries
+//! before. `valid_from` is `NULL` for the first version of rows that have
+//! not been inserted by naphtha. All times are UTC.
+//!
+//! The generated query trait of a `temporal` model additionally contains
+//! `history` returning all archived [Version]s of a model and
+//! `query_by_<primary_key>_as_of`, respectively `query_by_primary_key_as_of`
+//! for composite keys, returning the model as it was at the given time.
+
+use {
+    chrono::NaiveDateTime,
+    diesel::{
+        backend::Backend,
+        query_builder::QueryBuilder,
+        result::QueryResult,
+        Connection,
+    },
+};
+
+/// An archived version of a `temporal` model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Version<M> {
+    /// The model as it was stored.
+    pub model: M,
+    /// The time the version was written, `None` for the first version of a
+    /// row that has not been inserted by naphtha.
+    pub valid_from: Option<NaiveDateTime>,
+    /// The time the version was replaced by an update or removed.
+    pub valid_to: NaiveDateTime,
+}
+
+/// Provides the column definition of a SQL type in the history table of a
+/// `temporal` model on all database backends.
+///
+/// Implement it for custom SQL types used by `temporal` models.
+pub trait HistoryColumnType {
+    /// The column type on SQLite.
+    const SQLITE: &'static str;
+    /// The column type on MySQL.
+    const MYSQL: &'static str;
+    /// The column type on PostgreSQL.
+    const PG: &'static str;
+    /// If `true`, the column accepts `NULL` values.
+    const NULLABLE: bool = false;
+}
+
+macro_rules! impl_history_column_type {
+    ($($sql_type:ty => ($sqlite:expr, $mysql:expr, $pg:expr),)*) => {
+        $(
+            impl HistoryColumnType for $sql_type {
+                const SQLITE: &'static str = $sqlite;
+                const MYSQL: &'static str = $mysql;
+                const PG: &'static str = $pg;
+            }
+        )*
+    };
+}
+
+impl_history_column_type! {
+    diesel::sql_types::SmallInt => ("SMALLINT", "SMALLINT", "SMALLINT"),
+    diesel::sql_types::Integer => ("INTEGER", "INTEGER", "INTEGER"),
+    diesel::sql_types::BigInt => ("BIGINT", "BIGINT", "BIGINT"),
+    diesel::sql_types::Float => ("REAL", "FLOAT", "REAL"),
+    diesel::sql_types::Double => ("DOUBLE", "DOUBLE", "DOUBLE PRECISION"),
+    diesel::sql_types::Bool => ("BOOLEAN", "BOOLEAN", "BOOLEAN"),
+    diesel::sql_types::Text => ("TEXT", "TEXT", "TEXT"),
+    diesel::sql_types::Binary => ("BLOB", "BLOB", "BYTEA"),
+    diesel::sql_types::Date => ("DATE", "DATE", "DATE"),
+    diesel::sql_types::Timestamp => ("TIMESTAMP", "DATETIME(6)", "TIMESTAMP"),
+}
+
+#[cfg(all(feature = "uuid", any(feature = "sqlite", feature = "mysql")))]
+impl_history_column_type! {
+    crate::sql_types::Uuid => ("TEXT", "CHAR(36)", "UUID"),
+}
+
+#[cfg(all(feature = "uuid", feature = "pg"))]
+impl_history_column_type! {
+    diesel::sql_types::Uuid => ("TEXT", "CHAR(36)", "UUID"),
+}
+
+impl<T> HistoryColumnType for diesel::sql_types::Nullable<T>
+where
+    T: HistoryColumnType + diesel::sql_types::NotNull,
+{
+    const SQLITE: &'static str = T::SQLITE;
+    const MYSQL: &'static str = T::MYSQL;
+    const PG: &'static str = T::PG;
+    const NULLABLE: bool = true;
+}
+
+/// Returns the current time that is stored as `valid_to` of the replaced
+/// version, respectively of the version recording the insertion.
+#[doc(hidden)]
+pub fn now() -> NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+/// Quotes the identifiers for the backend of the connection, joined by
+/// commas.
+#[doc(hidden)]
+pub fn quote_identifiers<C>(_c: &C, identifiers: &[&str]) -> QueryResult<String>
+where
+    C: Connection,
+    <C::Backend as Backend>::QueryBuilder: Default,
+{
+    let mut quoted = Vec::with_capacity(identifiers.len());
+    for identifier in identifiers {
+        let mut builder = <C::Backend as Backend>::QueryBuilder::default();
+        builder.push_identifier(identifier)?;
+        quoted.push(builder.finish());
+    }
+    Ok(quoted.join(", "))
+}
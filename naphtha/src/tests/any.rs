@@ -0,0 +1,49 @@
+use crate::{AnyConnection, DatabaseConnect, DatabaseConnection};
+
+type Database = DatabaseConnection<AnyConnection>;
+
+#[test]
+fn from_connection() {
+    let c: diesel::SqliteConnection =
+        diesel::Connection::establish(":memory:").unwrap();
+    let _db: Database = DatabaseConnection::from(AnyConnection::from(c));
+}
+
+#[test]
+fn connect_sqlite() {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    assert!(matches!(&*db.lock().unwrap(), AnyConnection::Sqlite(_)));
+    let db: Database =
+        DatabaseConnection::connect("sqlite://:memory:").unwrap();
+    assert!(matches!(&*db.lock().unwrap(), AnyConnection::Sqlite(_)));
+}
+
+#[test]
+fn connect_unknown_scheme() {
+    let db: anyhow::Result<Database> =
+        DatabaseConnection::connect("oracle://localhost/db");
+    assert!(db.is_err());
+}
+
+#[cfg(not(feature = "pg"))]
+#[test]
+fn connect_disabled_backend() {
+    let db: anyhow::Result<Database> =
+        DatabaseConnection::connect("postgres://localhost/db");
+    assert!(db.is_err());
+}
+
+#[cfg(feature = "pg")]
+#[test]
+fn connect_pg() {
+    let db: Database = DatabaseConnection::connect(&super::pg_url()).unwrap();
+    assert!(matches!(&*db.lock().unwrap(), AnyConnection::Pg(_)));
+}
+
+#[cfg(feature = "mysql")]
+#[test]
+fn connect_mysql() {
+    let db: Database =
+        DatabaseConnection::connect(&super::mysql_url()).unwrap();
+    assert!(matches!(&*db.lock().unwrap(), AnyConnection::Mysql(_)));
+}
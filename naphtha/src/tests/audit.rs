@@ -0,0 +1,182 @@
+use {
+    crate::{
+        audit::{self, Audited},
+        model,
+        AnyConnection,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseInsertHandler,
+        DatabaseModelModifier,
+        DatabaseRemoveHandler,
+        DatabaseUpdateHandler,
+    },
+    diesel::RunQueryDsl,
+    serde_json_dep::json,
+};
+
+type Database = DatabaseConnection<diesel::SqliteConnection>;
+
+#[model(table_name = "accounts", primary_key = "id", audited)]
+pub struct Account {
+    id: i32,
+    pub login: String,
+    #[naphtha(sensitive)]
+    pub password_hash: String,
+    #[naphtha(skip)]
+    pub transient: Option<String>,
+}
+
+pub mod schema {
+    table! {
+        accounts (id) {
+            id -> Integer,
+            login -> Text,
+            password_hash -> Text,
+        }
+    }
+}
+
+impl_model!(Account, "accounts");
+
+#[cfg(any(
+    feature = "barrel-sqlite",
+    feature = "barrel-mysql",
+    feature = "barrel-pg"
+))]
+impl crate::barrel::DatabaseSqlMigration for Account {
+    fn migration_up(migration: &mut crate::barrel::Migration) {
+        use crate::barrel::types;
+        migration.create_table_if_not_exists("accounts", |t| {
+            t.add_column("id", types::primary());
+            t.add_column("login", types::text());
+            t.add_column("password_hash", types::text());
+        });
+    }
+
+    fn migration_down(migration: &mut crate::barrel::Migration) {
+        migration.drop_table_if_exists("accounts");
+    }
+}
+
+const CREATE_TABLE: &str = "CREATE TABLE accounts (id INTEGER PRIMARY KEY AUTOINCREMENT, login TEXT NOT NULL, password_hash TEXT NOT NULL)";
+
+fn account() -> Account {
+    Account {
+        id: 0,
+        login: "admin".into(),
+        password_hash: "$argon2id$first".into(),
+        transient: Some("not persisted".into()),
+    }
+}
+
+#[test]
+fn audit_log() {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| diesel::sql_query(CREATE_TABLE).execute(c))
+        .unwrap();
+    audit::create_table(&db).unwrap();
+
+    let mut account = account();
+    account.insert(&db).unwrap();
+    db.set_actor(Some("alice"));
+    account.login = "root".into();
+    account.password_hash = "$argon2id$second".into();
+    account.update(&db).unwrap();
+    // nothing changed
+    account.transient = None;
+    account.update(&db).unwrap();
+    db.set_actor(None);
+    account.remove(&db).unwrap();
+
+    let history = Account::audit_history(&db, &account.id).unwrap();
+    let operations: Vec<_> =
+        history.iter().map(|e| e.operation.as_str()).collect();
+    assert_eq!(operations, ["insert", "update", "update", "remove"]);
+    assert!(history.iter().all(|e| e.table_name == "accounts"));
+    assert!(history.iter().all(|e| e.primary_key == "1"));
+    let actors: Vec<_> = history.iter().map(|e| e.actor.as_deref()).collect();
+    assert_eq!(actors, [None, Some("alice"), Some("alice"), None]);
+
+    let changes: Vec<_> =
+        history.iter().map(|e| e.changes_json().unwrap()).collect();
+    assert_eq!(
+        changes[0],
+        json!({
+            "login": {"before": null, "after": "admin"},
+            "password_hash": {"before": null, "after": "<redacted>"},
+        })
+    );
+    assert_eq!(
+        changes[1],
+        json!({
+            "login": {"before": "admin", "after": "root"},
+            "password_hash": {"before": "<redacted>", "after": "<redacted>"},
+        })
+    );
+    assert_eq!(changes[2], json!({}));
+    assert_eq!(
+        changes[3],
+        json!({
+            "login": {"before": "root", "after": null},
+            "password_hash": {"before": "<redacted>", "after": null},
+        })
+    );
+    assert!(history.iter().all(|e| !e.changes.contains("argon2id")));
+
+    // removing a missing model is not recorded
+    account.remove(&db).unwrap();
+    assert_eq!(Account::audit_history(&db, &account.id).unwrap().len(), 4);
+}
+
+#[test]
+fn same_transaction() {
+    // the audit log table is missing
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| diesel::sql_query(CREATE_TABLE).execute(c))
+        .unwrap();
+
+    let mut account = account();
+    assert!(account.insert(&db).is_err());
+    let rows = db
+        .custom(|c| schema::accounts::table.load::<Account>(c))
+        .unwrap();
+    assert!(rows.is_empty());
+    assert!(Account::audit_history(&db, &1).is_err());
+}
+
+#[test]
+fn any_connection() {
+    let db: DatabaseConnection<AnyConnection> =
+        DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| match c {
+        AnyConnection::Sqlite(c) => diesel::sql_query(CREATE_TABLE).execute(c),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    })
+    .unwrap();
+    audit::create_table(&db).unwrap();
+
+    let mut account = account();
+    account.insert(&db).unwrap();
+    account.login = "root".into();
+    account.update(&db).unwrap();
+    account.remove(&db).unwrap();
+    let history = Account::audit_history(&db, &account.id).unwrap();
+    assert_eq!(history.len(), 3);
+}
+
+#[cfg(feature = "barrel-sqlite")]
+#[test]
+fn migration() {
+    use crate::barrel::DatabaseSqlMigrationExecutor;
+
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    Account::execute_migration_up(&db).unwrap();
+    let mut account = account();
+    account.insert(&db).unwrap();
+    assert_eq!(Account::audit_history(&db, &account.id).unwrap().len(), 1);
+
+    // the audit table is shared by all models and kept
+    Account::execute_migration_down(&db).unwrap();
+    assert_eq!(Account::audit_history(&db, &account.id).unwrap().len(), 1);
+}
@@ -0,0 +1,282 @@
+use {
+    crate::{
+        changes::{RowChange, RowOperation},
+        model,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseInsertHandler,
+        DatabaseModel,
+        DatabaseModelModifier,
+        DatabaseRemoveHandler,
+        DatabaseUpdateHandler,
+    },
+    diesel::{connection::SimpleConnection, Connection, RunQueryDsl},
+};
+
+type Database = DatabaseConnection<diesel::SqliteConnection>;
+
+#[model(table_name = "notes", primary_key = "id")]
+pub struct Note {
+    id: i32,
+    pub text: String,
+}
+
+pub mod schema {
+    table! {
+        notes (id) {
+            id -> Integer,
+            text -> Text,
+        }
+    }
+
+    table! {
+        select (id) {
+            id -> Integer,
+            from -> Text,
+        }
+    }
+}
+
+impl_model!(Note, "notes");
+impl_empty_migration!(Note);
+
+/// Table and column names that are keywords of SQL.
+#[model(table_name = "select", primary_key = "id")]
+pub struct Select {
+    id: i32,
+    #[naphtha(column = "from")]
+    pub source: String,
+}
+
+impl_model!(Select, "select");
+impl_empty_migration!(Select);
+
+fn setup() -> Database {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| {
+        c.batch_execute(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY AUTOINCREMENT, text TEXT NOT NULL);
+             CREATE TABLE others (id INTEGER PRIMARY KEY, value TEXT);",
+        )
+    })
+    .unwrap();
+    db
+}
+
+fn change(operation: RowOperation, rowid: i64) -> RowChange {
+    RowChange {
+        table: "notes".into(),
+        operation,
+        rowid,
+    }
+}
+
+#[test]
+fn model_operations() {
+    let db = setup();
+    let changes = db.watch::<Note>().unwrap();
+
+    let mut note = Note {
+        id: 0,
+        text: "first".into(),
+    };
+    note.insert(&db).unwrap();
+    note.text = "second".into();
+    note.update(&db).unwrap();
+    note.remove(&db).unwrap();
+
+    assert_eq!(
+        changes.try_iter().collect::<Vec<_>>(),
+        [
+            change(RowOperation::Insert, 1),
+            change(RowOperation::Update, 1),
+            change(RowOperation::Delete, 1),
+        ]
+    );
+}
+
+#[test]
+fn raw_sql() {
+    let db = setup();
+    let changes = db.watch_table("notes").unwrap();
+
+    db.custom(|c| {
+        c.batch_execute(
+            "INSERT INTO notes (text) VALUES ('a'), ('b');
+             INSERT INTO others (id, value) VALUES (1, 'ignored');
+             UPDATE notes SET text = 'c' WHERE id = 2;
+             DELETE FROM notes WHERE true;",
+        )
+    })
+    .unwrap();
+
+    assert_eq!(
+        changes.try_iter().collect::<Vec<_>>(),
+        [
+            change(RowOperation::Insert, 1),
+            change(RowOperation::Insert, 2),
+            change(RowOperation::Update, 2),
+            change(RowOperation::Delete, 1),
+            change(RowOperation::Delete, 2),
+        ]
+    );
+}
+
+#[test]
+fn reload() {
+    let db = setup();
+    let changes = db.watch::<Note>().unwrap();
+    let others = db.watch_table("others").unwrap();
+
+    db.custom(|c| {
+        c.batch_execute(
+            "INSERT INTO notes (text) VALUES ('external');
+             INSERT INTO others (id, value) VALUES (1, 'other');",
+        )
+    })
+    .unwrap();
+
+    let inserted = changes.try_recv().unwrap();
+    let note = inserted.reload::<Note>(&db).unwrap().unwrap();
+    assert_eq!(note.text, "external");
+    assert_eq!(note.primary_key() as i64, inserted.rowid);
+
+    // changes of other tables are not loaded as note
+    let other = others.try_recv().unwrap();
+    assert!(other.reload::<Note>(&db).unwrap().is_none());
+
+    db.custom(|c| diesel::sql_query("DELETE FROM notes WHERE true").execute(c))
+        .unwrap();
+    let deleted = changes.try_recv().unwrap();
+    assert_eq!(deleted.operation, RowOperation::Delete);
+    assert!(deleted.reload::<Note>(&db).unwrap().is_none());
+}
+
+#[test]
+fn quoted_identifiers() {
+    let db = setup();
+    db.custom(|c| {
+        c.batch_execute(
+            "CREATE TABLE \"select\" (id INTEGER PRIMARY KEY, \"from\" TEXT NOT NULL)",
+        )
+    })
+    .unwrap();
+    let changes = db.watch::<Select>().unwrap();
+
+    let mut select = Select {
+        id: 0,
+        source: "first".into(),
+    };
+    select.insert(&db).unwrap();
+    let inserted = changes.try_recv().unwrap();
+    let select = inserted.reload::<Select>(&db).unwrap().unwrap();
+    assert_eq!(select.source, "first");
+}
+
+#[test]
+fn multiple_watchers() {
+    let db = setup();
+    let first = db.watch_table("notes").unwrap();
+    // shares the watchers with db
+    let second = db.primary().watch_table("notes").unwrap();
+    let dropped = db.watch_table("notes").unwrap();
+    drop(dropped);
+
+    db.custom(|c| c.batch_execute("INSERT INTO notes (text) VALUES ('a')"))
+        .unwrap();
+    assert_eq!(first.try_recv().unwrap(), change(RowOperation::Insert, 1));
+    assert_eq!(second.try_recv().unwrap(), change(RowOperation::Insert, 1));
+}
+
+#[test]
+fn rolled_back_transaction() {
+    let db = setup();
+    let changes = db.watch_table("notes").unwrap();
+
+    let result = db.custom(|c| {
+        c.transaction::<(), diesel::result::Error, _>(|| {
+            c.batch_execute("INSERT INTO notes (text) VALUES ('a')")?;
+            Err(diesel::result::Error::RollbackTransaction)
+        })
+    });
+    assert!(result.is_err());
+    // reported on execution, the rollback is not
+    assert_eq!(changes.try_iter().count(), 1);
+}
+
+#[test]
+fn missing_table() {
+    let db = setup();
+    assert!(db.watch_table("missing").is_err());
+}
+
+#[test]
+fn without_rowid() {
+    let db = setup();
+    db.custom(|c| {
+        c.batch_execute(
+            "CREATE TABLE tags (name TEXT PRIMARY KEY) WITHOUT ROWID",
+        )
+    })
+    .unwrap();
+    let error = db.watch_table("tags").unwrap_err();
+    assert!(error.to_string().contains("WITHOUT ROWID"));
+    // the table can still be written
+    db.custom(|c| c.batch_execute("INSERT INTO tags (name) VALUES ('a')"))
+        .unwrap();
+}
+
+#[test]
+fn truncated_table() {
+    let db = setup();
+    let changes = db.watch_table("notes").unwrap();
+
+    db.custom(|c| {
+        c.batch_execute(
+            "INSERT INTO notes (text) VALUES ('a');
+             DELETE FROM notes;",
+        )
+    })
+    .unwrap();
+    // SQLite truncates the table without calling the update hook
+    assert_eq!(
+        changes.try_iter().collect::<Vec<_>>(),
+        [change(RowOperation::Insert, 1)]
+    );
+}
+
+#[test]
+fn reconnect() {
+    let path = std::env::temp_dir()
+        .join(format!("naphtha-{}-changes.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let db: Database =
+        DatabaseConnection::connect(&path.display().to_string()).unwrap();
+    db.custom(|c| {
+        c.batch_execute(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY AUTOINCREMENT, text TEXT NOT NULL)",
+        )
+    })
+    .unwrap();
+    let changes = db.watch_table("notes").unwrap();
+
+    db.reconnect().unwrap();
+    db.custom(|c| c.batch_execute("INSERT INTO notes (text) VALUES ('a')"))
+        .unwrap();
+    assert_eq!(changes.try_recv().unwrap(), change(RowOperation::Insert, 1));
+    drop(db);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn foreign_connection() {
+    let db: Database = diesel::SqliteConnection::establish(":memory:")
+        .unwrap()
+        .into();
+    db.custom(|c| {
+        c.batch_execute("CREATE TABLE notes (id INTEGER PRIMARY KEY)")
+    })
+    .unwrap();
+    let error = db.watch_table("notes").unwrap_err();
+    assert!(error.to_string().contains("established by naphtha"));
+}
@@ -0,0 +1,163 @@
+use {
+    crate::{
+        dirty::{DirtyTracking, Snapshot},
+        model,
+        AnyConnection,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseInsertHandler,
+        DatabaseModelModifier,
+        DatabaseRemoveHandler,
+        DatabaseUpdateHandler,
+    },
+    diesel::RunQueryDsl,
+};
+
+type Database = DatabaseConnection<diesel::SqliteConnection>;
+
+#[model(table_name = "profiles", primary_key = "id")]
+pub struct Profile {
+    id: i32,
+    pub name: String,
+    pub email: String,
+    pub bio: Option<String>,
+    #[naphtha(snapshot)]
+    snapshot: Snapshot,
+}
+
+pub mod schema {
+    table! {
+        profiles (id) {
+            id -> Integer,
+            name -> Text,
+            email -> Text,
+            bio -> Nullable<Text>,
+        }
+    }
+}
+
+impl_model!(Profile, "profiles");
+impl_empty_migration!(Profile);
+
+const CREATE_TABLE: &str = "CREATE TABLE profiles (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, email TEXT NOT NULL, bio TEXT)";
+
+fn setup() -> Database {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| diesel::sql_query(CREATE_TABLE).execute(c))
+        .unwrap();
+    db
+}
+
+fn profile(name: &str) -> Profile {
+    Profile {
+        id: 0,
+        name: name.into(),
+        email: format!("{}@example.com", name),
+        bio: Some("about me".into()),
+        snapshot: Snapshot::default(),
+    }
+}
+
+#[test]
+fn changed_fields() {
+    let db = setup();
+    let mut profile = profile("ada");
+    // nothing has been stored yet
+    assert_eq!(profile.changed_fields(), ["name", "email", "bio"]);
+
+    profile.insert(&db).unwrap();
+    assert!(!profile.is_dirty());
+    profile.email = "ada@example.org".into();
+    assert_eq!(profile.changed_fields(), ["email"]);
+    profile.update(&db).unwrap();
+    assert!(!profile.is_dirty());
+
+    let mut loaded = Profile::query_by_id(&db, &profile.id).unwrap();
+    assert!(!loaded.is_dirty());
+    loaded.bio = None;
+    assert_eq!(loaded.changed_fields(), ["bio"]);
+    loaded.mark_clean();
+    assert!(!loaded.is_dirty());
+}
+
+#[test]
+fn concurrent_updates() {
+    let db = setup();
+    let mut profile = profile("grace");
+    profile.insert(&db).unwrap();
+
+    let mut first = Profile::query_by_id(&db, &profile.id).unwrap();
+    let mut second = Profile::query_by_id(&db, &profile.id).unwrap();
+    first.name = "Grace".into();
+    second.email = "grace@example.org".into();
+    first.update(&db).unwrap();
+    second.update(&db).unwrap();
+
+    let stored = Profile::query_by_id(&db, &profile.id).unwrap();
+    assert_eq!(stored.name, "Grace");
+    assert_eq!(stored.email, "grace@example.org");
+}
+
+#[test]
+fn set_null() {
+    let db = setup();
+    let mut profile = profile("alan");
+    profile.insert(&db).unwrap();
+
+    profile.bio = None;
+    profile.update(&db).unwrap();
+    let stored = Profile::query_by_id(&db, &profile.id).unwrap();
+    assert_eq!(stored.bio, None);
+}
+
+#[test]
+fn skip_unchanged() {
+    let db = setup();
+    let mut profile = profile("edsger");
+    profile.insert(&db).unwrap();
+    db.custom(|c| diesel::sql_query("DELETE FROM profiles").execute(c))
+        .unwrap();
+
+    // no statement is executed, so the missing row is not noticed
+    profile.update(&db).unwrap();
+    profile.name = "Edsger".into();
+    assert!(profile.update(&db).is_err());
+    // the failed update keeps the change
+    assert!(profile.is_dirty());
+}
+
+#[test]
+fn snapshot_is_not_state() {
+    let first = profile("barbara");
+    let mut second = profile("barbara");
+    second.mark_clean();
+    assert_eq!(first.snapshot, second.snapshot);
+    assert_eq!(
+        format!("{:?}", second.snapshot),
+        "Snapshot { captured: true }"
+    );
+}
+
+#[test]
+fn any_connection() {
+    let db: DatabaseConnection<AnyConnection> =
+        DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| match c {
+        AnyConnection::Sqlite(c) => diesel::sql_query(CREATE_TABLE).execute(c),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    })
+    .unwrap();
+
+    let mut profile = profile("donald");
+    profile.insert(&db).unwrap();
+    let mut first = Profile::query_by_id(&db, &profile.id).unwrap();
+    let mut second = Profile::query_by_id(&db, &profile.id).unwrap();
+    first.name = "Donald".into();
+    second.bio = None;
+    first.update(&db).unwrap();
+    second.update(&db).unwrap();
+
+    let stored = Profile::query_by_id(&db, &profile.id).unwrap();
+    assert_eq!((stored.name.as_str(), stored.bio), ("Donald", None));
+}
@@ -0,0 +1,141 @@
+use {
+    crate::{
+        events::ModelEvent,
+        model,
+        AnyConnection,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseInsertHandler,
+        DatabaseModelModifier,
+        DatabaseRemoveHandler,
+        DatabaseUpdateHandler,
+    },
+    diesel::RunQueryDsl,
+    std::thread,
+};
+
+type Database = DatabaseConnection<diesel::SqliteConnection>;
+
+#[model(table_name = "tasks", primary_key = "id", events)]
+#[derive(Clone, PartialEq)]
+pub struct Task {
+    id: i32,
+    pub title: String,
+}
+
+pub mod schema {
+    table! {
+        tasks (id) {
+            id -> Integer,
+            title -> Text,
+        }
+    }
+}
+
+impl_model!(Task, "tasks");
+impl_empty_migration!(Task);
+
+const CREATE_TABLE: &str = "CREATE TABLE tasks (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL UNIQUE)";
+
+fn setup() -> Database {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| diesel::sql_query(CREATE_TABLE).execute(c))
+        .unwrap();
+    db
+}
+
+fn task(title: &str) -> Task {
+    Task {
+        id: 0,
+        title: title.into(),
+    }
+}
+
+/// Returns the name of the event variant with the title and primary key of
+/// the model.
+fn describe(event: &ModelEvent<Task>) -> (&'static str, String, i32) {
+    let name = match event {
+        ModelEvent::Inserted { .. } => "inserted",
+        ModelEvent::Updated { .. } => "updated",
+        ModelEvent::Removed { .. } => "removed",
+    };
+    (name, event.model().title.clone(), *event.primary_key())
+}
+
+#[test]
+fn subscribe() {
+    let db = setup();
+    let events = db.subscribe::<Task>();
+
+    let mut task = task("write tests");
+    task.insert(&db).unwrap();
+    task.title = "write more tests".into();
+    task.update(&db).unwrap();
+    task.remove(&db).unwrap();
+    // removing a missing model does not publish an event
+    task.remove(&db).unwrap();
+
+    let received = events.try_iter().collect::<Vec<_>>();
+    let received = received.iter().map(describe).collect::<Vec<_>>();
+    assert_eq!(
+        received,
+        [
+            ("inserted", "write tests".to_string(), 1),
+            ("updated", "write more tests".to_string(), 1),
+            ("removed", "write more tests".to_string(), 1),
+        ]
+    );
+}
+
+#[test]
+fn failed_operations() {
+    let db = setup();
+    task("duplicate").insert(&db).unwrap();
+    let events = db.subscribe::<Task>();
+
+    // violates the unique constraint, nothing is committed
+    assert!(task("duplicate").insert(&db).is_err());
+    let mut missing = task("missing");
+    missing.id = 42;
+    assert!(missing.update(&db).is_err());
+    assert!(events.try_recv().is_err());
+}
+
+#[test]
+fn multiple_subscribers() {
+    let db = setup();
+    let first = db.subscribe::<Task>();
+    // shares the subscribers with db
+    let second = db.primary().subscribe::<Task>();
+    let dropped = db.subscribe::<Task>();
+    drop(dropped);
+
+    let receiver = thread::spawn(move || second.recv().unwrap());
+    let mut task = task("notify");
+    task.insert(&db).unwrap();
+
+    let event = first.try_recv().unwrap();
+    assert!(
+        matches!(&event, ModelEvent::Inserted { model, primary_key: 1 } if *model == task)
+    );
+    assert_eq!(describe(&receiver.join().unwrap()), describe(&event));
+}
+
+#[test]
+fn any_connection() {
+    let db: DatabaseConnection<AnyConnection> =
+        DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| match c {
+        AnyConnection::Sqlite(c) => diesel::sql_query(CREATE_TABLE).execute(c),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    })
+    .unwrap();
+    let events = db.subscribe::<Task>();
+
+    let mut task = task("any");
+    task.insert(&db).unwrap();
+    task.update(&db).unwrap();
+    task.remove(&db).unwrap();
+    assert_eq!(events.try_iter().count(), 3);
+}
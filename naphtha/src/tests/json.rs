@@ -0,0 +1,201 @@
+use {
+    crate::{
+        dirty::Snapshot,
+        json::{JsonTransfer, BATCH_SIZE},
+        model,
+        AnyConnection,
+        ConnectionError,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseInsertHandler,
+        DatabaseModelModifier,
+        DatabaseRemoveHandler,
+        DatabaseUpdateHandler,
+    },
+    diesel::{connection::SimpleConnection, RunQueryDsl},
+};
+
+type Database = DatabaseConnection<diesel::SqliteConnection>;
+
+#[model(table_name = "accounts", primary_key = "id", serde)]
+pub struct Account {
+    id: i32,
+    #[naphtha(column = "login")]
+    pub name: String,
+    #[naphtha(sensitive)]
+    pub password_hash: String,
+    pub note: Option<String>,
+    #[naphtha(skip)]
+    pub session: Option<String>,
+    #[naphtha(snapshot)]
+    snapshot: Snapshot,
+}
+
+#[model(table_name = "memberships", primary_key = "team_id, user_id", serde)]
+pub struct Membership {
+    team_id: i32,
+    user_id: i32,
+    pub role: String,
+}
+
+pub mod schema {
+    table! {
+        accounts (id) {
+            id -> Integer,
+            login -> Text,
+            password_hash -> Text,
+            note -> Nullable<Text>,
+        }
+    }
+
+    table! {
+        memberships (team_id, user_id) {
+            team_id -> Integer,
+            user_id -> Integer,
+            role -> Text,
+        }
+    }
+}
+
+impl_model!(Account, "accounts");
+impl_empty_migration!(Account);
+impl_model!(Membership, "memberships", (team_id, user_id): (i32, i32) = (0, 0));
+impl_empty_migration!(Membership);
+
+const CREATE_TABLES: &str = "CREATE TABLE accounts (id INTEGER PRIMARY KEY AUTOINCREMENT, login TEXT NOT NULL UNIQUE, password_hash TEXT NOT NULL, note TEXT);
+    CREATE TABLE memberships (team_id INTEGER NOT NULL, user_id INTEGER NOT NULL, role TEXT NOT NULL, PRIMARY KEY (team_id, user_id));";
+
+fn setup() -> Database {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| c.batch_execute(CREATE_TABLES)).unwrap();
+    db
+}
+
+fn account(name: &str) -> Account {
+    Account {
+        id: 0,
+        name: name.into(),
+        password_hash: "secret".into(),
+        note: None,
+        session: Some("session".into()),
+        snapshot: Snapshot::default(),
+    }
+}
+
+fn export<M: JsonTransfer<diesel::SqliteConnection>>(db: &Database) -> String {
+    let mut output = vec![];
+    M::export_json(db, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn attributes() {
+    let db = setup();
+    let mut account = account("ada");
+    account.note = Some("admin".into());
+    account.insert(&db).unwrap();
+
+    assert_eq!(
+        export::<Account>(&db),
+        "{\"id\":1,\"login\":\"ada\",\"password_hash\":\"secret\",\"note\":\"admin\"}\n"
+    );
+    let account: Account = crate::serde_json::from_str(
+        "{\"id\":2,\"login\":\"grace\",\"password_hash\":\"hash\",\"note\":null}",
+    )
+    .unwrap();
+    assert_eq!(account.name, "grace");
+    assert_eq!(account.password_hash, "hash");
+    assert_eq!(account.session, None);
+}
+
+#[test]
+fn round_trip() {
+    let db = setup();
+    let models = BATCH_SIZE as i32 * 2 + 3;
+    for i in 0..models {
+        account(&format!("user{}", i)).insert(&db).unwrap();
+    }
+    // gaps in the primary keys are preserved
+    db.custom(|c| {
+        diesel::sql_query("DELETE FROM accounts WHERE id = 2").execute(c)
+    })
+    .unwrap();
+    let output = export::<Account>(&db);
+    assert_eq!(output.lines().count(), models as usize - 1);
+
+    let other = setup();
+    let imported = Account::import_json(&other, output.as_bytes()).unwrap();
+    assert_eq!(imported, models as usize - 1);
+    assert_eq!(export::<Account>(&other), output);
+    let last = Account::query_by_id(&other, &models).unwrap();
+    assert_eq!(last.name, format!("user{}", models - 1));
+    // sensitive members are restored
+    assert_eq!(last.password_hash, "secret");
+    assert!(Account::query_by_id(&other, &2).is_err());
+}
+
+#[test]
+fn composite_primary_key() {
+    let db = setup();
+    for (team_id, user_id) in [(2, 1), (1, 2), (1, 1)] {
+        let mut membership = Membership {
+            team_id,
+            user_id,
+            role: "member".into(),
+        };
+        membership.insert(&db).unwrap();
+    }
+    let output = export::<Membership>(&db);
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        [
+            "{\"team_id\":1,\"user_id\":1,\"role\":\"member\"}",
+            "{\"team_id\":1,\"user_id\":2,\"role\":\"member\"}",
+            "{\"team_id\":2,\"user_id\":1,\"role\":\"member\"}",
+        ]
+    );
+
+    let other = setup();
+    assert_eq!(
+        Membership::import_json(&other, output.as_bytes()).unwrap(),
+        3
+    );
+    assert_eq!(export::<Membership>(&other), output);
+}
+
+#[test]
+fn failed_import() {
+    let db = setup();
+    let input =
+        "{\"id\":1,\"login\":\"a\",\"password_hash\":\"\",\"note\":null}\n\n{\"id\":2,\"login\":\"b\"";
+    let error = Account::import_json(&db, input.as_bytes()).unwrap_err();
+    assert!(matches!(
+        &error,
+        ConnectionError::Query(diesel::result::Error::DeserializationError(e))
+            if e.to_string().starts_with("line 3:")
+    ));
+
+    // violates the unique constraint
+    let input = "{\"id\":1,\"login\":\"a\",\"password_hash\":\"\",\"note\":null}\n{\"id\":2,\"login\":\"a\",\"password_hash\":\"\",\"note\":null}\n";
+    assert!(Account::import_json(&db, input.as_bytes()).is_err());
+    // nothing is imported
+    assert_eq!(export::<Account>(&db), "");
+}
+
+#[test]
+fn any_connection() {
+    let db: DatabaseConnection<AnyConnection> =
+        DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| match c {
+        AnyConnection::Sqlite(c) => c.batch_execute(CREATE_TABLES),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    })
+    .unwrap();
+
+    let input = "{\"id\":7,\"login\":\"any\",\"password_hash\":\"hash\",\"note\":\"imported\"}\n";
+    assert_eq!(Account::import_json(&db, input.as_bytes()).unwrap(), 1);
+    let mut output = vec![];
+    assert_eq!(Account::export_json(&db, &mut output).unwrap(), 1);
+    assert_eq!(String::from_utf8(output).unwrap(), input);
+}
@@ -0,0 +1,50 @@
+//! A logger collecting the messages logged by the current thread.
+
+use {
+    log::{LevelFilter, Log, Metadata, Record},
+    std::{
+        sync::{Mutex, Once},
+        thread::ThreadId,
+    },
+};
+
+static MESSAGES: Mutex<Vec<(ThreadId, String, String)>> = Mutex::new(vec![]);
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        MESSAGES.lock().unwrap().push((
+            std::thread::current().id(),
+            record.target().to_string(),
+            record.args().to_string(),
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Runs the given function and returns the messages logged by it with the
+/// given target.
+pub fn capture<F: FnOnce()>(target: &str, f: F) -> Vec<String> {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&Logger).unwrap();
+        log::set_max_level(LevelFilter::Trace);
+    });
+    let thread = std::thread::current().id();
+    f();
+    let mut messages = MESSAGES.lock().unwrap();
+    let (captured, others) = messages
+        .drain(..)
+        .partition::<Vec<_>, _>(|(t, tg, _)| *t == thread && tg == target);
+    *messages = others;
+    captured
+        .into_iter()
+        .map(|(_, _, message)| message)
+        .collect()
+}
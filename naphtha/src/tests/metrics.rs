@@ -0,0 +1,128 @@
+//! A minimal recorder collecting the metrics recorded by naphtha.
+
+use {
+    metrics_dep::{
+        with_local_recorder,
+        Counter,
+        CounterFn,
+        Gauge,
+        GaugeFn,
+        Histogram,
+        HistogramFn,
+        Key,
+        KeyName,
+        Metadata,
+        Recorder,
+        SharedString,
+        Unit,
+    },
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// The values of all recorded metrics by name and labels, e.g.
+/// `naphtha_operations_total{model=Person,operation=insert}`. Counters and
+/// gauges contain their value, histograms the number of recorded values.
+#[derive(Debug, Default, Clone)]
+pub struct CapturedMetrics {
+    values: Arc<Mutex<HashMap<String, f64>>>,
+}
+
+impl CapturedMetrics {
+    /// Returns the value of the given metric, `0` if it has not been
+    /// recorded.
+    pub fn get(&self, key: &str) -> f64 {
+        *self.values.lock().unwrap().get(key).unwrap_or(&0.0)
+    }
+
+    fn handle(&self, key: &Key) -> Arc<Handle> {
+        let labels: Vec<_> = key
+            .labels()
+            .map(|l| format!("{}={}", l.key(), l.value()))
+            .collect();
+        let name = match labels.is_empty() {
+            true => key.name().to_string(),
+            false => format!("{}{{{}}}", key.name(), labels.join(",")),
+        };
+        Arc::new(Handle {
+            name,
+            values: self.values.clone(),
+        })
+    }
+}
+
+struct Handle {
+    name: String,
+    values: Arc<Mutex<HashMap<String, f64>>>,
+}
+
+impl Handle {
+    fn update(&self, f: impl FnOnce(&mut f64)) {
+        f(self
+            .values
+            .lock()
+            .unwrap()
+            .entry(self.name.clone())
+            .or_default());
+    }
+}
+
+impl CounterFn for Handle {
+    fn increment(&self, value: u64) {
+        self.update(|v| *v += value as f64);
+    }
+
+    fn absolute(&self, value: u64) {
+        self.update(|v| *v = value as f64);
+    }
+}
+
+impl GaugeFn for Handle {
+    fn increment(&self, value: f64) {
+        self.update(|v| *v += value);
+    }
+
+    fn decrement(&self, value: f64) {
+        self.update(|v| *v -= value);
+    }
+
+    fn set(&self, value: f64) {
+        self.update(|v| *v = value);
+    }
+}
+
+impl HistogramFn for Handle {
+    fn record(&self, _value: f64) {
+        self.update(|v| *v += 1.0);
+    }
+}
+
+impl Recorder for CapturedMetrics {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {
+    }
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        Counter::from_arc(self.handle(key))
+    }
+
+    fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::from_arc(self.handle(key))
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(self.handle(key))
+    }
+}
+
+/// Runs the given function and returns the metrics recorded by it.
+pub fn capture<F: FnOnce()>(f: F) -> CapturedMetrics {
+    let metrics = CapturedMetrics::default();
+    with_local_recorder(&metrics, f);
+    metrics
+}
@@ -0,0 +1,872 @@
+use {
+    crate::{
+        model,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseInsertHandler,
+        DatabaseModelModifier,
+        DatabaseRemoveHandler,
+        DatabaseUpdateHandler,
+    },
+    diesel::RunQueryDsl,
+};
+
+type Database = DatabaseConnection<diesel::SqliteConnection>;
+
+#[model(table_name = "persons", primary_key = "id")]
+pub struct Person {
+    id: i32,
+    #[naphtha(column = "desc_text")]
+    pub description: String,
+    #[naphtha(skip)]
+    pub transient: Option<String>,
+}
+
+pub mod schema {
+    table! {
+        persons (id) {
+            id -> Integer,
+            desc_text -> Text,
+        }
+    }
+}
+
+impl_model!(Person, "persons");
+
+impl_empty_migration!(Person);
+
+fn setup() -> Database {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| {
+        diesel::sql_query(
+            "CREATE TABLE persons (id INTEGER PRIMARY KEY AUTOINCREMENT, desc_text TEXT NOT NULL)",
+        )
+        .execute(c)
+    })
+    .unwrap();
+    db
+}
+
+fn person(description: &str) -> Person {
+    Person {
+        id: 0,
+        description: description.into(),
+        transient: Some("not persisted".into()),
+    }
+}
+
+#[test]
+fn renamed_column() {
+    let db = setup();
+    let mut p = person("first");
+    p.insert(&db).unwrap();
+    assert_eq!(p.id, 1);
+
+    let queried = Person::query_by_description(&db, &"first".into()).unwrap();
+    assert_eq!(queried.len(), 1);
+    assert_eq!(queried[0].id, 1);
+
+    p.description = "updated".into();
+    p.update(&db).unwrap();
+    let queried = Person::query_by_id(&db, &1).unwrap();
+    assert_eq!(queried.description, "updated");
+
+    p.remove(&db).unwrap();
+    assert!(Person::query_by_id(&db, &1).is_err());
+}
+
+#[test]
+fn skipped_member() {
+    let db = setup();
+    let mut p = person("first");
+    p.insert(&db).unwrap();
+    p.update(&db).unwrap();
+
+    let queried = Person::query_by_id(&db, &p.id).unwrap();
+    assert_eq!(queried.transient, None);
+}
+
+#[test]
+fn any_connection() {
+    use crate::AnyConnection;
+    let db: DatabaseConnection<AnyConnection> =
+        DatabaseConnection::connect("sqlite://:memory:").unwrap();
+    db.custom(|c| match c {
+        AnyConnection::Sqlite(c) => diesel::sql_query(
+            "CREATE TABLE persons (id INTEGER PRIMARY KEY AUTOINCREMENT, desc_text TEXT NOT NULL)",
+        )
+        .execute(c),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    })
+    .unwrap();
+
+    let mut p = person("first");
+    p.insert(&db).unwrap();
+    assert_eq!(p.id, 1);
+    person("second").insert(&db).unwrap();
+
+    let queried = Person::query_by_description(&db, &"first".into()).unwrap();
+    assert_eq!(queried.len(), 1);
+    assert_eq!(Person::query_by_ids(&db, &[1, 2]).unwrap().len(), 2);
+
+    p.description = "updated".into();
+    p.update(&db).unwrap();
+    assert_eq!(Person::query_by_id(&db, &1).unwrap().description, "updated");
+
+    p.remove(&db).unwrap();
+    assert!(Person::query_by_id(&db, &1).is_err());
+}
+
+#[test]
+fn lock_timeout() {
+    use {crate::ConnectionError, std::time::Duration};
+    let db = setup().with_lock_timeout(Duration::from_millis(10));
+    let mut p = person("first");
+    p.insert(&db).unwrap();
+
+    let c = db.lock().unwrap();
+    let error = p.update(&db).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<ConnectionError>(),
+        Some(ConnectionError::LockTimeout(_))
+    ));
+    assert!(error.to_string().starts_with(
+        "Timed out waiting for the lock on DatabaseModifier::update"
+    ));
+    assert!(matches!(
+        Person::query_by_id(&db, &p.id),
+        Err(ConnectionError::LockTimeout(_))
+    ));
+    drop(c);
+    assert!(Person::query_by_id(&db, &p.id).is_ok());
+}
+
+#[test]
+fn read_replica() {
+    use crate::DatabaseConfig;
+    let file = |name: &str| {
+        let path = std::env::temp_dir().join(format!(
+            "naphtha-{}-{}.db",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path.display().to_string()
+    };
+    let (primary, replica) = (file("primary"), file("replica"));
+    let config = DatabaseConfig::new(&primary).replica(&replica);
+    let db: Database = DatabaseConnection::connect_config(&config).unwrap();
+    assert_eq!(db.replica_count(), 1);
+    for url in [&primary, &replica] {
+        let c: Database = DatabaseConnection::connect(url).unwrap();
+        c.custom(|c| {
+            diesel::sql_query(
+                "CREATE TABLE persons (id INTEGER PRIMARY KEY AUTOINCREMENT, desc_text TEXT NOT NULL)",
+            )
+            .execute(c)
+        })
+        .unwrap();
+    }
+
+    // writes go to the primary, reads to the replica
+    let mut p = person("first");
+    p.insert(&db).unwrap();
+    assert!(Person::query_by_id(&db, &p.id).is_err());
+    assert_eq!(
+        Person::query_by_id(&db.primary(), &p.id)
+            .unwrap()
+            .description,
+        "first"
+    );
+
+    // the replica catches up
+    let replicated: Database = DatabaseConnection::connect(&replica).unwrap();
+    person("first").insert(&replicated).unwrap();
+    assert_eq!(
+        Person::query_by_id(&db, &p.id).unwrap().description,
+        "first"
+    );
+
+    p.remove(&db).unwrap();
+    assert!(Person::query_by_id(&db.primary(), &p.id).is_err());
+    assert!(Person::query_by_id(&db, &p.id).is_ok());
+
+    drop((db, replicated));
+    for url in [&primary, &replica] {
+        std::fs::remove_file(url).unwrap();
+    }
+}
+
+#[test]
+fn slow_query_log() {
+    use {super::logger::capture, crate::SlowQueryLog, std::time::Duration};
+    let log = SlowQueryLog::new(Duration::ZERO);
+    let db = setup().with_slow_query_log(log);
+    let mut p = person("secret");
+    let messages = capture("naphtha::slow_query", || {
+        p.insert(&db).unwrap();
+        Person::query_by_description(&db, &"secret".into()).unwrap();
+    });
+    assert_eq!(messages.len(), 2);
+    let line = line!() - 4;
+    for message in &messages {
+        assert!(message.starts_with("Slow query of model Person at "));
+        assert!(message.contains(&format!("{}:", file!())));
+        assert!(message.contains("-- 1 binds: [redacted]"));
+        assert!(!message.contains("secret"));
+    }
+    assert!(messages[0].contains(&format!(":{}:", line)));
+    assert!(messages[0].contains("INSERT INTO `persons` (`desc_text`)"));
+    assert!(messages[1].contains(&format!(":{}:", line + 1)));
+    assert!(messages[1].contains(
+        "SELECT `persons`.`id`, `persons`.`desc_text` FROM `persons` \
+         WHERE `persons`.`desc_text` = ?"
+    ));
+
+    let db = db.with_slow_query_log(log.with_bind_values());
+    let messages = capture("naphtha::slow_query", || {
+        Person::query_by_id(&db, &p.id).unwrap();
+        p.update(&db).unwrap();
+        p.remove(&db).unwrap();
+    });
+    assert_eq!(messages.len(), 3);
+    assert!(messages[0].ends_with("LIMIT ? -- 2 binds: [1, 1]"));
+    assert!(messages[1].contains("UPDATE `persons` SET `desc_text` = ?"));
+    assert!(messages[1].ends_with("-- 2 binds: [\"secret\", 1]"));
+    assert!(messages[2].contains("DELETE FROM `persons`"));
+
+    let db = setup().with_slow_query_log(SlowQueryLog::new(Duration::MAX));
+    let messages = capture("naphtha::slow_query", || {
+        person("fast").insert(&db).unwrap();
+    });
+    assert!(messages.is_empty());
+}
+
+#[test]
+fn slow_query_binds() {
+    use {
+        super::logger::capture,
+        crate::SlowQueryLog,
+        diesel::sql_types::{Integer, Nullable, Text},
+        std::{panic::Location, time::Duration},
+    };
+    let db = setup();
+    let log = SlowQueryLog::new(Duration::ZERO);
+    // pretty printed, the optional values span multiple lines
+    let query = || {
+        diesel::sql_query("SELECT ?, ?, ?")
+            .bind::<Nullable<Text>, _>(Some("multi\nline"))
+            .bind::<Nullable<Integer>, _>(None::<i32>)
+            .bind::<Integer, _>(1)
+    };
+    let run = |log: &SlowQueryLog| {
+        db.custom(|c| {
+            SlowQueryLog::run(
+                Some(log),
+                "Person",
+                Location::caller(),
+                c,
+                query,
+                |q| q.execute(c),
+            )
+        })
+        .unwrap();
+    };
+    let messages = capture("naphtha::slow_query", || {
+        run(&log);
+        run(&log.with_bind_values());
+    });
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].ends_with("SELECT ?, ?, ? -- 3 binds: [redacted]"));
+    assert!(
+        messages[1].ends_with("-- 3 binds: [Some(\"multi\\nline\"), None, 1]")
+    );
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_spans() {
+    use super::tracing::capture;
+    let db = setup();
+    let mut p = person("first");
+    let spans = capture(|| {
+        p.insert(&db).unwrap();
+        p.update(&db).unwrap();
+        Person::query_by_id(&db, &p.id).unwrap();
+        assert!(Person::query_by_id(&db, &42).is_err());
+        p.remove(&db).unwrap();
+        db.custom(|_| ());
+    });
+    let names: Vec<_> = spans.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "insert",
+            "update",
+            "query_by_id",
+            "query_by_id",
+            "remove",
+            "custom"
+        ]
+    );
+    for span in &spans[..5] {
+        assert_eq!(span.field("model"), "Person");
+        assert_eq!(span.field("table"), "persons");
+        assert!(span.has("lock_wait_us"));
+        assert!(span.has("duration_us"));
+    }
+    assert_eq!(spans[0].field("primary_key"), "1");
+    assert_eq!(spans[0].field("rows_affected"), "1");
+    assert_eq!(spans[1].field("rows_affected"), "1");
+    assert_eq!(spans[2].field("primary_key"), "1");
+    assert!(!spans[2].has("error"));
+    assert_eq!(spans[3].field("primary_key"), "42");
+    assert_eq!(spans[3].field("error"), "NotFound");
+    assert_eq!(spans[4].field("rows_affected"), "1");
+    assert!(spans[5].has("lock_wait_us"));
+    assert!(spans[5].has("duration_us"));
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics() {
+    use {super::metrics::capture, std::time::Duration};
+    let db = setup().with_lock_timeout(Duration::from_millis(10));
+    let mut p = person("first");
+    let metrics = capture(|| {
+        p.insert(&db).unwrap();
+        p.update(&db).unwrap();
+        Person::query_by_id(&db, &p.id).unwrap();
+        assert!(Person::query_by_id(&db, &42).is_err());
+        p.remove(&db).unwrap();
+        db.custom(|_| ());
+
+        let c = db.lock().unwrap();
+        assert!(p.update(&db).is_err());
+        drop(c);
+    });
+    let operation = |model: &str, operation: &str, outcome: &str| {
+        let labels = format!(
+            "model={},operation={},outcome={}",
+            model, operation, outcome
+        );
+        let total = format!("naphtha_operations_total{{{}}}", labels);
+        let duration =
+            format!("naphtha_operation_duration_seconds{{{}}}", labels);
+        assert_eq!(metrics.get(&total), metrics.get(&duration));
+        metrics.get(&total)
+    };
+    assert_eq!(operation("Person", "insert", "success"), 1.0);
+    assert_eq!(operation("Person", "update", "success"), 1.0);
+    assert_eq!(operation("Person", "update", "error"), 1.0);
+    assert_eq!(operation("Person", "query_by_id", "success"), 1.0);
+    assert_eq!(operation("Person", "query_by_id", "error"), 1.0);
+    assert_eq!(operation("Person", "remove", "success"), 1.0);
+    assert_eq!(
+        metrics
+            .get("naphtha_operations_total{operation=custom,outcome=success}"),
+        1.0
+    );
+    assert_eq!(metrics.get("naphtha_lock_waiting"), 0.0);
+    // including the wait of `DatabaseConnection::lock`
+    assert_eq!(metrics.get("naphtha_lock_wait_seconds"), 8.0);
+    assert_eq!(metrics.get("naphtha_lock_timeouts_total"), 1.0);
+}
+
+/// Makes sure that a model implements the traits of all backends at once.
+#[cfg(all(feature = "sqlite", feature = "mysql", feature = "pg"))]
+#[test]
+fn all_backends() {
+    fn modifier<T, M: DatabaseModelModifier<T>>() {}
+    modifier::<diesel::SqliteConnection, Person>();
+    modifier::<diesel::MysqlConnection, Person>();
+    modifier::<diesel::PgConnection, Person>();
+    modifier::<crate::AnyConnection, Person>();
+
+    #[cfg(all(
+        feature = "barrel-sqlite",
+        feature = "barrel-mysql",
+        feature = "barrel-pg"
+    ))]
+    {
+        use crate::barrel::DatabaseSqlMigrationExecutor;
+        fn executor<T, M: DatabaseSqlMigrationExecutor<T, usize>>() {}
+        executor::<diesel::SqliteConnection, Person>();
+        executor::<diesel::MysqlConnection, Person>();
+        executor::<diesel::PgConnection, Person>();
+        executor::<crate::AnyConnection, Person>();
+    }
+}
+
+mod composite {
+    use {
+        super::Database,
+        crate::{
+            model,
+            DatabaseConnect,
+            DatabaseConnection,
+            DatabaseInsertHandler,
+            DatabaseModelModifier,
+            DatabaseRemoveHandler,
+            DatabaseUpdateHandler,
+        },
+        diesel::RunQueryDsl,
+    };
+
+    #[model(table_name = "memberships", primary_key = "tenant_id, user_id")]
+    pub struct Membership {
+        tenant_id: i32,
+        user_id: i32,
+        pub role: String,
+    }
+
+    pub mod schema {
+        table! {
+            memberships (tenant_id, user_id) {
+                tenant_id -> Integer,
+                user_id -> Integer,
+                role -> Text,
+            }
+        }
+    }
+
+    impl_model!(Membership, "memberships", (tenant_id, user_id): (i32, i32) = (0, 0));
+
+    impl_empty_migration!(Membership);
+
+    fn setup() -> Database {
+        let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+        db.custom(|c| {
+            diesel::sql_query(
+                "CREATE TABLE memberships (tenant_id INTEGER NOT NULL, user_id INTEGER NOT NULL, role TEXT NOT NULL, PRIMARY KEY (tenant_id, user_id))",
+            )
+            .execute(c)
+        })
+        .unwrap();
+        db
+    }
+
+    fn membership(tenant_id: i32, user_id: i32, role: &str) -> Membership {
+        Membership {
+            tenant_id,
+            user_id,
+            role: role.into(),
+        }
+    }
+
+    #[test]
+    fn composite_primary_key() {
+        let db = setup();
+        let mut admin = membership(1, 1, "admin");
+        admin.insert(&db).unwrap();
+        membership(1, 2, "user").insert(&db).unwrap();
+        membership(2, 1, "user").insert(&db).unwrap();
+
+        let queried = Membership::query_by_primary_key(&db, &(1, 2)).unwrap();
+        assert_eq!(queried.role, "user");
+        assert_eq!(Membership::query_by_tenant_id(&db, &1).unwrap().len(), 2);
+        let queried =
+            Membership::query_by_primary_keys(&db, &[(1, 1), (2, 1)]).unwrap();
+        assert_eq!(queried.len(), 2);
+        assert!(Membership::query_by_primary_keys(&db, &[])
+            .unwrap()
+            .is_empty());
+
+        admin.role = "owner".into();
+        admin.update(&db).unwrap();
+        let queried = Membership::query_by_primary_key(&db, &(1, 1)).unwrap();
+        assert_eq!(queried.role, "owner");
+        assert_eq!(
+            Membership::query_by_primary_key(&db, &(1, 2)).unwrap().role,
+            "user"
+        );
+
+        admin.remove(&db).unwrap();
+        assert!(Membership::query_by_primary_key(&db, &(1, 1)).is_err());
+        assert_eq!(Membership::query_by_user_id(&db, &1).unwrap().len(), 1);
+    }
+}
+
+#[cfg(all(feature = "uuid", not(feature = "pg")))]
+mod client_key {
+    use {
+        super::Database,
+        crate::{
+            model,
+            types::Uuid,
+            DatabaseConnect,
+            DatabaseConnection,
+            DatabaseInsertHandler,
+            DatabaseModelModifier,
+            DatabaseRemoveHandler,
+            DatabaseUpdateHandler,
+        },
+        diesel::RunQueryDsl,
+    };
+
+    #[model(
+        table_name = "devices",
+        primary_key = "id",
+        key_strategy = "client"
+    )]
+    pub struct Device {
+        id: Uuid,
+        pub name: String,
+    }
+
+    pub mod schema {
+        table! {
+            use diesel::sql_types::*;
+            use crate::sql_types::Uuid;
+
+            devices (id) {
+                id -> Uuid,
+                name -> Text,
+            }
+        }
+    }
+
+    impl_model!(Device, "devices", id: Uuid = Uuid::nil());
+
+    impl_empty_migration!(Device);
+
+    #[test]
+    fn client_generated_uuid() {
+        let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+        db.custom(|c| {
+            diesel::sql_query(
+                "CREATE TABLE devices (id TEXT PRIMARY KEY NOT NULL, name TEXT NOT NULL)",
+            )
+            .execute(c)
+        })
+        .unwrap();
+
+        let id = Uuid::new_v4();
+        let mut device = Device {
+            id,
+            name: "sensor".into(),
+        };
+        device.insert(&db).unwrap();
+        assert_eq!(device.id, id);
+        Device {
+            id: Uuid::new_v4(),
+            name: "gateway".into(),
+        }
+        .insert(&db)
+        .unwrap();
+
+        assert_eq!(Device::query_by_id(&db, &id).unwrap().name, "sensor");
+        assert_eq!(Device::query_by_ids(&db, &[id]).unwrap().len(), 1);
+
+        device.name = "thermometer".into();
+        device.update(&db).unwrap();
+        assert_eq!(Device::query_by_id(&db, &id).unwrap().name, "thermometer");
+
+        device.remove(&db).unwrap();
+        assert!(Device::query_by_id(&db, &id).is_err());
+    }
+}
+
+mod generic {
+    use {
+        super::Database,
+        crate::{
+            model,
+            DatabaseConnect,
+            DatabaseConnection,
+            DatabaseInsertHandler,
+            DatabaseModelModifier,
+            DatabaseRemoveHandler,
+            DatabaseUpdateHandler,
+        },
+        diesel::RunQueryDsl,
+        std::borrow::Cow,
+    };
+
+    #[model(table_name = "notes", primary_key = "id")]
+    pub struct Note<'a, T> {
+        id: i32,
+        pub title: Cow<'a, str>,
+        pub payload: T,
+    }
+
+    pub mod schema {
+        table! {
+            notes (id) {
+                id -> Integer,
+                title -> Text,
+                payload -> Text,
+            }
+        }
+    }
+
+    impl<'a, T> crate::DatabaseModel for Note<'a, T> {
+        type PrimaryKey = i32;
+
+        fn primary_key(&self) -> Self::PrimaryKey {
+            self.id
+        }
+
+        fn set_primary_key(&mut self, value: &Self::PrimaryKey) {
+            self.id = *value;
+        }
+
+        fn default_primary_key() -> Self::PrimaryKey {
+            0
+        }
+
+        fn table_name() -> &'static str {
+            "notes"
+        }
+    }
+
+    impl<'a, T, DB> DatabaseInsertHandler<DB> for Note<'a, T> {}
+    impl<'a, T, DB> DatabaseUpdateHandler<DB> for Note<'a, T> {}
+    impl<'a, T, DB> DatabaseRemoveHandler<DB> for Note<'a, T> {}
+
+    #[test]
+    fn generic_model() {
+        let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+        db.custom(|c| {
+            diesel::sql_query(
+                "CREATE TABLE notes (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, payload TEXT NOT NULL)",
+            )
+            .execute(c)
+        })
+        .unwrap();
+
+        let title = String::from("borrowed title");
+        let mut note = Note {
+            id: 0,
+            title: Cow::Borrowed(&title),
+            payload: String::from("payload"),
+        };
+        note.insert(&db).unwrap();
+
+        let queried: Note<String> = Note::query_by_id(&db, &note.id).unwrap();
+        assert_eq!(queried.title, "borrowed title");
+        let queried: Vec<Note<String>> =
+            Note::query_by_payload(&db, &"payload".into()).unwrap();
+        assert_eq!(queried.len(), 1);
+
+        note.payload = String::from("changed");
+        note.update(&db).unwrap();
+        let queried: Note<String> = Note::query_by_id(&db, &note.id).unwrap();
+        assert_eq!(queried.payload, "changed");
+
+        note.remove(&db).unwrap();
+        assert!(Note::<String>::query_by_id(&db, &1).is_err());
+    }
+}
+
+mod custom_schema {
+    use {
+        super::Database,
+        crate::{
+            model,
+            DatabaseConnect,
+            DatabaseConnection,
+            DatabaseInsertHandler,
+            DatabaseModelModifier,
+            DatabaseRemoveHandler,
+            DatabaseUpdateHandler,
+        },
+        diesel::{BelongingToDsl, RunQueryDsl},
+    };
+
+    pub mod db {
+        pub mod tables {
+            table! {
+                accounts (id) {
+                    id -> Integer,
+                    login -> Text,
+                    secret -> Text,
+                }
+            }
+
+            table! {
+                sessions (id) {
+                    id -> Integer,
+                    account_id -> Integer,
+                }
+            }
+        }
+    }
+
+    #[model(
+        table_name = "accounts",
+        primary_key = "id",
+        schema = "crate::tests::model::custom_schema::db::tables",
+        derive = "Clone"
+    )]
+    pub struct Account {
+        id: i32,
+        pub login: String,
+        pub secret: String,
+    }
+
+    impl ::std::fmt::Debug for Account {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+            f.debug_struct("Account")
+                .field("id", &self.id)
+                .field("login", &self.login)
+                .finish()
+        }
+    }
+
+    impl_model!(Account, "accounts");
+
+    impl_empty_migration!(Account);
+
+    #[model(
+        table_name = "sessions",
+        primary_key = "id",
+        schema = "self::db::tables"
+    )]
+    #[belongs_to(Account)]
+    pub struct Session {
+        pub id: i32,
+        pub account_id: i32,
+    }
+
+    impl_model!(Session, "sessions");
+
+    impl_empty_migration!(Session);
+
+    #[test]
+    fn schema_path_and_derive() {
+        let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+        db.custom(|c| {
+            diesel::sql_query(
+                "CREATE TABLE accounts (id INTEGER PRIMARY KEY AUTOINCREMENT, login TEXT NOT NULL, secret TEXT NOT NULL)",
+            )
+            .execute(c)?;
+            diesel::sql_query(
+                "CREATE TABLE sessions (id INTEGER PRIMARY KEY AUTOINCREMENT, account_id INTEGER NOT NULL)",
+            )
+            .execute(c)
+        })
+        .unwrap();
+
+        let mut account = Account {
+            id: 0,
+            login: "admin".into(),
+            secret: "hunter2".into(),
+        };
+        account.insert(&db).unwrap();
+        assert!(!format!("{:?}", account.clone()).contains("hunter2"));
+
+        for _ in 0..2 {
+            Session {
+                id: 0,
+                account_id: account.id,
+            }
+            .insert(&db)
+            .unwrap();
+        }
+        let sessions = db
+            .custom(|c| Session::belonging_to(&account).load::<Session>(c))
+            .unwrap();
+        assert_eq!(sessions.len(), 2);
+        let sessions = Session::query_by_account_id(&db, &account.id).unwrap();
+        assert_eq!(sessions.len(), 2);
+
+        account.secret = "correct horse".into();
+        account.update(&db).unwrap();
+        let queried = Account::query_by_id(&db, &account.id).unwrap();
+        assert_eq!(queried.secret, "correct horse");
+    }
+}
+
+mod sensitive {
+    use {
+        super::Database,
+        crate::{
+            model,
+            DatabaseConnect,
+            DatabaseConnection,
+            DatabaseInsertHandler,
+            DatabaseModelModifier,
+            DatabaseRemoveHandler,
+            DatabaseUpdateHandler,
+        },
+    };
+
+    pub mod schema {
+        table! {
+            users (id) {
+                id -> Integer,
+                login -> Text,
+                password_hash -> Text,
+            }
+        }
+
+        table! {
+            tokens (id) {
+                id -> Integer,
+                value -> Text,
+            }
+        }
+    }
+
+    #[model(table_name = "users", primary_key = "id")]
+    pub struct User {
+        id: i32,
+        pub login: String,
+        #[naphtha(sensitive)]
+        pub password_hash: String,
+    }
+
+    impl_model!(User, "users");
+
+    impl_empty_migration!(User);
+
+    // does not implement Debug at all
+    #[model(
+        table_name = "tokens",
+        primary_key = "id",
+        derive = "",
+        log = "primary_key"
+    )]
+    pub struct Token {
+        id: i32,
+        pub value: String,
+    }
+
+    impl_model!(Token, "tokens");
+
+    impl_empty_migration!(Token);
+
+    #[test]
+    fn redacted_debug_and_errors() {
+        // the tables are missing, so every operation fails
+        let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+
+        let mut user = User {
+            id: 0,
+            login: "admin".into(),
+            password_hash: "$argon2id$secret".into(),
+        };
+        let debug = format!("{:?}", user);
+        assert!(debug.contains("login: \"admin\""));
+        assert!(debug.contains("password_hash: <redacted>"));
+        assert!(!debug.contains("secret"));
+
+        let error = format!("{:#}", user.insert(&db).unwrap_err());
+        assert!(error.contains("Model:\nUser {"), "{}", error);
+        assert!(error.contains("<redacted>"));
+        assert!(!error.contains("secret"));
+        let error = format!("{:#}", user.update(&db).unwrap_err());
+        assert!(error.contains("<redacted>"));
+        assert!(!error.contains("secret"));
+
+        let mut token = Token {
+            id: 42,
+            value: "secret".into(),
+        };
+        let error = format!("{:#}", token.remove(&db).unwrap_err());
+        assert!(error.contains("Primary key: 42"), "{}", error);
+        assert!(!error.contains("secret"));
+    }
+}
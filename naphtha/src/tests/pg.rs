@@ -0,0 +1,155 @@
+#[cfg(test)]
+type Database = crate::DatabaseConnection<diesel::PgConnection>;
+
+#[test]
+fn from_connection() {
+    let c: diesel::PgConnection =
+        diesel::Connection::establish(&super::pg_url()).unwrap();
+    let _db: Database = crate::DatabaseConnection::from(c);
+}
+
+#[test]
+fn from_arc_mutex_connection() {
+    use std::sync::{Arc, Mutex};
+    let c: diesel::PgConnection =
+        diesel::Connection::establish(&super::pg_url()).unwrap();
+    let c = Arc::new(Mutex::new(c));
+    let _db: Database = crate::DatabaseConnection::from(c);
+}
+
+#[test]
+fn connect() {
+    use crate::{DatabaseConnect, DatabaseConnection};
+    let _db: Database = DatabaseConnection::connect(&super::pg_url()).unwrap();
+}
+
+#[cfg(all(
+    test,
+    feature = "uuid",
+    not(any(feature = "sqlite", feature = "mysql"))
+))]
+mod client_key {
+    use {
+        super::Database,
+        crate::{
+            model,
+            uuid::Uuid,
+            DatabaseConnect,
+            DatabaseConnection,
+            DatabaseInsertHandler,
+            DatabaseModelModifier,
+            DatabaseRemoveHandler,
+            DatabaseUpdateHandler,
+        },
+        diesel::RunQueryDsl,
+    };
+
+    #[model(
+        table_name = "devices",
+        primary_key = "id",
+        key_strategy = "client"
+    )]
+    pub struct Device {
+        id: Uuid,
+        pub name: String,
+    }
+
+    pub mod schema {
+        table! {
+            devices (id) {
+                id -> Uuid,
+                name -> Text,
+            }
+        }
+    }
+
+    impl_model!(Device, "devices", id: Uuid = Uuid::nil());
+    impl_empty_migration!(Device);
+
+    #[test]
+    fn native_uuid() {
+        let db: Database =
+            DatabaseConnection::connect(&super::super::pg_url()).unwrap();
+        db.custom(|c| {
+            diesel::sql_query(
+                "CREATE TEMPORARY TABLE devices (id UUID PRIMARY KEY, name TEXT NOT NULL)",
+            )
+            .execute(c)
+        })
+        .unwrap();
+
+        let id = Uuid::new_v4();
+        let mut device = Device {
+            id,
+            name: "sensor".into(),
+        };
+        device.insert(&db).unwrap();
+        assert_eq!(Device::query_by_id(&db, &id).unwrap().name, "sensor");
+
+        device.name = "thermometer".into();
+        device.update(&db).unwrap();
+        assert_eq!(Device::query_by_id(&db, &id).unwrap().name, "thermometer");
+
+        device.remove(&db).unwrap();
+        assert!(Device::query_by_id(&db, &id).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod json {
+    use {
+        super::Database,
+        crate::{
+            json::JsonTransfer,
+            model,
+            DatabaseConnect,
+            DatabaseConnection,
+            DatabaseInsertHandler,
+            DatabaseModelModifier,
+            DatabaseRemoveHandler,
+            DatabaseUpdateHandler,
+        },
+        diesel::RunQueryDsl,
+    };
+
+    #[model(table_name = "entries", primary_key = "id", serde)]
+    pub struct Entry {
+        id: i32,
+        pub text: String,
+    }
+
+    pub mod schema {
+        table! {
+            entries (id) {
+                id -> Int4,
+                text -> Text,
+            }
+        }
+    }
+
+    impl_model!(Entry, "entries");
+    impl_empty_migration!(Entry);
+
+    #[test]
+    fn import_advances_sequence() {
+        let db: Database =
+            DatabaseConnection::connect(&super::super::pg_url()).unwrap();
+        db.custom(|c| {
+            diesel::sql_query(
+                "CREATE TEMPORARY TABLE entries (id SERIAL PRIMARY KEY, text TEXT NOT NULL)",
+            )
+            .execute(c)
+        })
+        .unwrap();
+
+        let input = "{\"id\":1,\"text\":\"a\"}\n{\"id\":5,\"text\":\"b\"}\n";
+        assert_eq!(Entry::import_json(&db, input.as_bytes()).unwrap(), 2);
+
+        let mut entry = Entry {
+            id: 0,
+            text: "c".into(),
+        };
+        entry.insert(&db).unwrap();
+        assert_eq!(entry.id, 6);
+    }
+}
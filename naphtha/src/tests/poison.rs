@@ -0,0 +1,106 @@
+use {
+    crate::{
+        ConnectionError,
+        DatabaseConnect,
+        DatabaseConnection,
+        PoisonPolicy,
+    },
+    diesel::{Connection, RunQueryDsl},
+    std::panic::{catch_unwind, AssertUnwindSafe},
+};
+
+type Database = DatabaseConnection<diesel::SqliteConnection>;
+
+fn setup(poison_policy: PoisonPolicy) -> Database {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| {
+        diesel::sql_query("CREATE TABLE marker (id INTEGER)").execute(c)
+    })
+    .unwrap();
+    db.with_poison_policy(poison_policy)
+}
+
+/// Panics while a transaction is open, leaving it behind.
+fn poison(db: &Database) {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        db.custom(|c| {
+            c.transaction::<(), diesel::result::Error, _>(|| {
+                diesel::sql_query("INSERT INTO marker VALUES (1)")
+                    .execute(c)?;
+                panic!("poisoning the connection lock");
+            })
+        })
+    }));
+    assert!(result.is_err());
+    assert!(db.primary.connection.is_poisoned());
+}
+
+fn markers(db: &Database) -> Result<i64, ConnectionError> {
+    use diesel::{dsl::sql, sql_types::BigInt};
+    db.write(|c| {
+        diesel::select(sql::<BigInt>("(SELECT COUNT(*) FROM marker)"))
+            .get_result(c)
+    })
+}
+
+#[test]
+fn fail() {
+    let db = setup(PoisonPolicy::Fail);
+    poison(&db);
+    assert!(matches!(markers(&db), Err(ConnectionError::Lock(_))));
+    assert!(matches!(db.try_lock(), Err(ConnectionError::Lock(_))));
+    assert!(matches!(db.lock(), Err(ConnectionError::Lock(_))));
+    assert!(matches!(
+        db.try_custom(|_| ()),
+        Err(ConnectionError::Lock(_))
+    ));
+}
+
+#[test]
+fn recover() {
+    let db = setup(PoisonPolicy::Recover);
+    poison(&db);
+    // the open transaction has been rolled back
+    assert_eq!(markers(&db).unwrap(), 0);
+    assert!(db.lock().is_ok());
+    db.custom(|c| {
+        c.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::sql_query("INSERT INTO marker VALUES (1)").execute(c)
+        })
+    })
+    .unwrap();
+    assert_eq!(markers(&db).unwrap(), 1);
+}
+
+#[test]
+fn reconnect() {
+    let db = setup(PoisonPolicy::Reconnect);
+    poison(&db);
+    // the new in memory database does not contain the table
+    assert!(matches!(markers(&db), Err(ConnectionError::Query(_))));
+    assert!(db.lock().is_ok());
+}
+
+#[test]
+fn reconnect_without_connector() {
+    let c: diesel::SqliteConnection =
+        diesel::Connection::establish(":memory:").unwrap();
+    diesel::sql_query("CREATE TABLE marker (id INTEGER)")
+        .execute(&c)
+        .unwrap();
+    let db = Database::from(c).with_poison_policy(PoisonPolicy::Reconnect);
+    poison(&db);
+    assert!(matches!(markers(&db), Err(ConnectionError::Lock(_))));
+}
+
+#[test]
+fn parse() {
+    for policy in [
+        PoisonPolicy::Fail,
+        PoisonPolicy::Recover,
+        PoisonPolicy::Reconnect,
+    ] {
+        assert_eq!(policy.to_string().parse::<PoisonPolicy>(), Ok(policy));
+    }
+    assert!("ignore".parse::<PoisonPolicy>().is_err());
+}
@@ -0,0 +1,175 @@
+use {
+    crate::{
+        model,
+        AnyConnection,
+        ConnectionError,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseInsertHandler,
+        DatabaseModelModifier,
+        DatabaseRemoveHandler,
+        DatabaseUpdateHandler,
+    },
+    diesel::{connection::SimpleConnection, RunQueryDsl},
+};
+
+type Database = DatabaseConnection<diesel::SqliteConnection>;
+
+#[model(table_name = "articles", primary_key = "id", reload_on_insert)]
+pub struct Article {
+    id: i32,
+    pub title: String,
+    pub slug: String,
+    #[naphtha(skip)]
+    pub draft: Option<String>,
+}
+
+#[model(table_name = "articles", primary_key = "id")]
+pub struct Stale {
+    id: i32,
+    pub title: String,
+    pub slug: String,
+}
+
+pub mod schema {
+    table! {
+        articles (id) {
+            id -> Integer,
+            title -> Text,
+            slug -> Text,
+        }
+    }
+}
+
+impl_model!(Article, "articles");
+impl_empty_migration!(Article);
+impl_model!(Stale, "articles");
+impl_empty_migration!(Stale);
+
+// the slug is computed by the database
+const CREATE_TABLE: &str = "CREATE TABLE articles (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, slug TEXT NOT NULL);
+    CREATE TRIGGER articles_slug AFTER INSERT ON articles BEGIN
+        UPDATE articles SET slug = lower(replace(NEW.title, ' ', '-')) WHERE id = NEW.id;
+    END;";
+
+fn setup() -> Database {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| c.batch_execute(CREATE_TABLE)).unwrap();
+    db
+}
+
+#[test]
+fn reload_on_insert() {
+    let db = setup();
+    let mut article = Article {
+        id: 0,
+        title: "Hello World".into(),
+        slug: String::new(),
+        draft: Some("unsaved".into()),
+    };
+    article.insert(&db).unwrap();
+    assert_eq!(article.id, 1);
+    assert_eq!(article.slug, "hello-world");
+    assert_eq!(article.draft.as_deref(), Some("unsaved"));
+
+    // not reloaded without the flag
+    let mut stale = Stale {
+        id: 0,
+        title: "Second Article".into(),
+        slug: String::new(),
+    };
+    stale.insert(&db).unwrap();
+    assert_eq!(stale.slug, "");
+    stale.reload(&db).unwrap();
+    assert_eq!(stale.slug, "second-article");
+}
+
+#[test]
+fn external_change() {
+    let db = setup();
+    let mut stale = Stale {
+        id: 0,
+        title: "Title".into(),
+        slug: String::new(),
+    };
+    stale.insert(&db).unwrap();
+    db.custom(|c| {
+        diesel::sql_query("UPDATE articles SET title = 'Changed'").execute(c)
+    })
+    .unwrap();
+
+    stale.reload(&db).unwrap();
+    assert_eq!(stale.title, "Changed");
+    assert_eq!(stale.slug, "title");
+}
+
+#[test]
+fn removed_row() {
+    let db = setup();
+    let mut stale = Stale {
+        id: 0,
+        title: "Gone".into(),
+        slug: String::new(),
+    };
+    stale.insert(&db).unwrap();
+    db.custom(|c| diesel::sql_query("DELETE FROM articles").execute(c))
+        .unwrap();
+
+    let error = stale.reload(&db).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<ConnectionError>(),
+        Some(ConnectionError::Query(diesel::result::Error::NotFound))
+    ));
+    // the model is left unchanged
+    assert_eq!(stale.title, "Gone");
+}
+
+#[test]
+fn any_connection() {
+    let db: DatabaseConnection<AnyConnection> =
+        DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| match c {
+        AnyConnection::Sqlite(c) => c.batch_execute(CREATE_TABLE),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    })
+    .unwrap();
+
+    let mut article = Article {
+        id: 0,
+        title: "Any Connection".into(),
+        slug: String::new(),
+        draft: None,
+    };
+    article.insert(&db).unwrap();
+    assert_eq!(article.slug, "any-connection");
+    article.reload(&db).unwrap();
+    assert_eq!(article.title, "Any Connection");
+}
+
+/// Implements the modifier manually, without reload.
+struct Manual;
+
+impl<T> DatabaseInsertHandler<T> for Manual {}
+impl<T> DatabaseUpdateHandler<T> for Manual {}
+impl<T> DatabaseRemoveHandler<T> for Manual {}
+
+impl<T> DatabaseModelModifier<T> for Manual {
+    fn insert(&mut self, _conn: &DatabaseConnection<T>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn remove(&mut self, _conn: &DatabaseConnection<T>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn update(&mut self, _conn: &DatabaseConnection<T>) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn default_reload() {
+    let db = setup();
+    assert!(Manual.reload(&db).is_err());
+}
@@ -0,0 +1,152 @@
+use {
+    crate::{
+        retry::is_connection_error,
+        ConnectionError,
+        DatabaseConnect,
+        DatabaseConnection,
+        RetryPolicy,
+    },
+    diesel::{
+        result::{DatabaseErrorKind, Error},
+        RunQueryDsl,
+    },
+    std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    },
+};
+
+type Database = DatabaseConnection<diesel::SqliteConnection>;
+
+fn broken_connection() -> Error {
+    Error::DatabaseError(
+        DatabaseErrorKind::__Unknown,
+        Box::new("MySQL server has gone away".to_string()),
+    )
+}
+
+/// Connects to a new in memory database containing the table `marker`.
+/// The table is gone after reconnecting.
+fn setup(retry_policy: RetryPolicy) -> Database {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| {
+        diesel::sql_query("CREATE TABLE marker (id INTEGER)").execute(c)
+    })
+    .unwrap();
+    db.with_retry_policy(retry_policy.initial_backoff(Duration::from_millis(1)))
+}
+
+fn has_marker(db: &Database) -> bool {
+    db.custom(|c| diesel::sql_query("SELECT * FROM marker").execute(c))
+        .is_ok()
+}
+
+#[test]
+fn connection_errors() {
+    assert!(is_connection_error(&broken_connection()));
+    assert!(is_connection_error(&Error::DatabaseError(
+        DatabaseErrorKind::UnableToSendCommand,
+        Box::new("".to_string()),
+    )));
+    assert!(!is_connection_error(&Error::DatabaseError(
+        DatabaseErrorKind::UniqueViolation,
+        Box::new("UNIQUE constraint failed".to_string()),
+    )));
+    assert!(!is_connection_error(&Error::NotFound));
+}
+
+#[test]
+fn backoff() {
+    let policy = RetryPolicy::new(5)
+        .initial_backoff(Duration::from_millis(100))
+        .max_backoff(Duration::from_millis(300));
+    assert_eq!(policy.backoff(0), Duration::from_millis(100));
+    assert_eq!(policy.backoff(1), Duration::from_millis(200));
+    assert_eq!(policy.backoff(2), Duration::from_millis(300));
+    assert_eq!(policy.backoff(40), Duration::from_millis(300));
+}
+
+#[test]
+fn idempotent_operation_is_retried() {
+    let db = setup(RetryPolicy::new(2));
+    let attempts = AtomicUsize::new(0);
+    let result = db.write_idempotent(|_| {
+        match attempts.fetch_add(1, Ordering::SeqCst) {
+            0 | 1 => Err(broken_connection()),
+            _ => Ok(42),
+        }
+    });
+    assert_eq!(result.unwrap(), 42);
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    assert!(!has_marker(&db));
+}
+
+#[test]
+fn retries_are_limited() {
+    let db = setup(RetryPolicy::new(1));
+    let attempts = AtomicUsize::new(0);
+    let result = db.read(|_| -> diesel::QueryResult<()> {
+        attempts.fetch_add(1, Ordering::SeqCst);
+        Err(broken_connection())
+    });
+    assert!(matches!(
+        result,
+        Err(ConnectionError::Query(e)) if is_connection_error(&e)
+    ));
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn write_reconnects_without_retry() {
+    let db = setup(RetryPolicy::default());
+    let attempts = AtomicUsize::new(0);
+    let result = db.write(|_| -> diesel::QueryResult<()> {
+        attempts.fetch_add(1, Ordering::SeqCst);
+        Err(broken_connection())
+    });
+    assert!(matches!(result, Err(ConnectionError::Query(_))));
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    // the next operation uses the new connection
+    assert!(!has_marker(&db));
+}
+
+#[test]
+fn other_errors_are_not_retried() {
+    let db = setup(RetryPolicy::default());
+    let attempts = AtomicUsize::new(0);
+    let result = db.write_idempotent(|_| -> diesel::QueryResult<()> {
+        attempts.fetch_add(1, Ordering::SeqCst);
+        Err(Error::NotFound)
+    });
+    assert!(matches!(
+        result,
+        Err(ConnectionError::Query(Error::NotFound))
+    ));
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    assert!(has_marker(&db));
+}
+
+#[test]
+fn connection_without_connector() {
+    let c: diesel::SqliteConnection =
+        diesel::Connection::establish(":memory:").unwrap();
+    let db = Database::from(c);
+    let attempts = AtomicUsize::new(0);
+    let result = db.write_idempotent(|_| -> diesel::QueryResult<()> {
+        attempts.fetch_add(1, Ordering::SeqCst);
+        Err(broken_connection())
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    assert!(db.reconnect().is_err());
+}
+
+#[test]
+fn health_check() {
+    let db = setup(RetryPolicy::default())
+        .with_replica(DatabaseConnection::connect(":memory:").unwrap());
+    db.health_check().unwrap();
+    db.reconnect().unwrap();
+    assert!(!has_marker(&db));
+    db.health_check().unwrap();
+}
@@ -22,3 +22,65 @@ fn connect() {
     use crate::{DatabaseConnect, DatabaseConnection};
     let _db: Database = DatabaseConnection::connect(":memory:").unwrap();
 }
+
+#[test]
+fn try_lock() {
+    use crate::{ConnectionError, DatabaseConnect, DatabaseConnection};
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    {
+        let _c = db.try_lock().unwrap();
+        assert!(matches!(
+            db.try_lock(),
+            Err(ConnectionError::LockTimeout(t)) if t.is_zero()
+        ));
+    }
+    assert!(db.try_lock().is_ok());
+}
+
+#[test]
+fn lock_timeout() {
+    use {
+        crate::{ConnectionError, DatabaseConnect, DatabaseConnection},
+        std::time::{Duration, Instant},
+    };
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    let timeout = Duration::from_millis(20);
+    let _c = db.lock().unwrap();
+    let start = Instant::now();
+    assert!(matches!(
+        db.lock_timeout(timeout),
+        Err(ConnectionError::LockTimeout(t)) if t == timeout
+    ));
+    assert!(start.elapsed() >= timeout);
+}
+
+#[test]
+fn custom_lock_timeout() {
+    use {
+        crate::{ConnectionError, DatabaseConnect, DatabaseConnection},
+        std::time::Duration,
+    };
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    let db = db.with_lock_timeout(Duration::from_millis(10));
+    {
+        let _c = db.lock().unwrap();
+        assert!(matches!(db.lock(), Err(ConnectionError::LockTimeout(_))));
+        assert!(matches!(
+            db.try_custom(|_| ()),
+            Err(ConnectionError::LockTimeout(_))
+        ));
+    }
+    assert_eq!(db.try_custom(|_| 42).unwrap(), 42);
+
+    // custom ignores the timeout and waits until the lock is released
+    let (locked, wait) = std::sync::mpsc::channel();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let _c = db.lock().unwrap();
+            locked.send(()).unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+        });
+        wait.recv().unwrap();
+        assert_eq!(db.custom(|_| 42), 42);
+    });
+}
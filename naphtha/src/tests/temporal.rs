@@ -0,0 +1,326 @@
+use {
+    crate::{
+        model,
+        temporal::{self, Version},
+        AnyConnection,
+        ConnectionError,
+        DatabaseConnect,
+        DatabaseConnection,
+        DatabaseInsertHandler,
+        DatabaseModelModifier,
+        DatabaseRemoveHandler,
+        DatabaseUpdateHandler,
+    },
+    diesel::RunQueryDsl,
+    std::{thread, time::Duration},
+};
+
+type Database = DatabaseConnection<diesel::SqliteConnection>;
+
+#[model(table_name = "documents", primary_key = "id", temporal)]
+pub struct Document {
+    id: i32,
+    pub title: String,
+    pub body: Option<String>,
+    #[naphtha(skip)]
+    pub transient: Option<String>,
+}
+
+pub mod schema {
+    table! {
+        documents (id) {
+            id -> Integer,
+            title -> Text,
+            body -> Nullable<Text>,
+        }
+    }
+
+    table! {
+        revisions (document_id, revision) {
+            document_id -> Integer,
+            revision -> Integer,
+            content -> Text,
+        }
+    }
+
+    table! {
+        order (id) {
+            id -> Integer,
+            group -> Text,
+        }
+    }
+}
+
+impl_model!(Document, "documents");
+
+#[cfg(any(
+    feature = "barrel-sqlite",
+    feature = "barrel-mysql",
+    feature = "barrel-pg"
+))]
+impl crate::barrel::DatabaseSqlMigration for Document {
+    fn migration_up(migration: &mut crate::barrel::Migration) {
+        use crate::barrel::types;
+        migration.create_table_if_not_exists("documents", |t| {
+            t.add_column("id", types::primary());
+            t.add_column("title", types::text());
+            t.add_column("body", types::text().nullable(true));
+        });
+    }
+
+    fn migration_down(migration: &mut crate::barrel::Migration) {
+        migration.drop_table_if_exists("documents");
+    }
+}
+
+#[model(
+    table_name = "revisions",
+    primary_key = "document_id, revision",
+    temporal
+)]
+#[derive(PartialEq)]
+pub struct Revision {
+    document_id: i32,
+    revision: i32,
+    pub content: String,
+}
+
+impl_model!(Revision, "revisions", (document_id, revision): (i32, i32) = (0, 0));
+impl_empty_migration!(Revision);
+
+/// Table and column names that are keywords of SQL.
+#[model(table_name = "order", primary_key = "id", temporal)]
+pub struct Order {
+    id: i32,
+    #[naphtha(column = "group")]
+    pub group_name: String,
+}
+
+impl_model!(Order, "order");
+impl_empty_migration!(Order);
+
+const CREATE_TABLES: &[&str] = &[
+    "CREATE TABLE documents (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, body TEXT)",
+    "CREATE TABLE documents_history (history_id INTEGER PRIMARY KEY AUTOINCREMENT, id INTEGER NOT NULL, title TEXT NOT NULL, body TEXT, valid_from TIMESTAMP, valid_to TIMESTAMP NOT NULL)",
+    "CREATE TABLE revisions (document_id INTEGER NOT NULL, revision INTEGER NOT NULL, content TEXT NOT NULL, PRIMARY KEY (document_id, revision))",
+    "CREATE TABLE revisions_history (history_id INTEGER PRIMARY KEY AUTOINCREMENT, document_id INTEGER NOT NULL, revision INTEGER NOT NULL, content TEXT NOT NULL, valid_from TIMESTAMP, valid_to TIMESTAMP NOT NULL)",
+    "CREATE TABLE `order` (id INTEGER PRIMARY KEY AUTOINCREMENT, `group` TEXT NOT NULL)",
+    "CREATE TABLE order_history (history_id INTEGER PRIMARY KEY AUTOINCREMENT, id INTEGER NOT NULL, `group` TEXT NOT NULL, valid_from TIMESTAMP, valid_to TIMESTAMP NOT NULL)",
+];
+
+fn setup() -> Database {
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    for table in CREATE_TABLES {
+        db.custom(|c| diesel::sql_query(*table).execute(c)).unwrap();
+    }
+    db
+}
+
+fn document(title: &str) -> Document {
+    Document {
+        id: 0,
+        title: title.into(),
+        body: None,
+        transient: Some("not persisted".into()),
+    }
+}
+
+/// Returns the current time, making sure that it differs from the time of
+/// the operations before and after.
+fn tick() -> chrono::NaiveDateTime {
+    thread::sleep(Duration::from_millis(2));
+    let now = temporal::now();
+    thread::sleep(Duration::from_millis(2));
+    now
+}
+
+#[test]
+fn history_and_as_of() {
+    let db = setup();
+    let mut doc = document("draft");
+    let before = tick();
+    doc.insert(&db).unwrap();
+    let inserted = tick();
+    doc.title = "review".into();
+    doc.body = Some("text".into());
+    doc.update(&db).unwrap();
+    let updated = tick();
+    doc.title = "final".into();
+    doc.update(&db).unwrap();
+    let current = tick();
+
+    let history = Document::history(&db, &doc.id).unwrap();
+    let titles: Vec<_> =
+        history.iter().map(|v| v.model.title.as_str()).collect();
+    assert_eq!(titles, ["draft", "review"]);
+    // the first version is valid since the insertion
+    let valid_from = history[0].valid_from.unwrap();
+    assert!(valid_from > before && valid_from < inserted);
+    assert_eq!(history[1].valid_from, Some(history[0].valid_to));
+    assert!(history[0].valid_to > inserted && history[0].valid_to < updated);
+    assert!(history[1].valid_to > updated && history[1].valid_to < current);
+    assert_eq!(history[1].model.body.as_deref(), Some("text"));
+    assert_eq!(history[1].model.transient, None);
+
+    let id = doc.id;
+    let as_of = |t| Document::query_by_id_as_of(&db, &id, t).unwrap();
+    assert!(matches!(
+        Document::query_by_id_as_of(&db, &id, before),
+        Err(ConnectionError::Query(diesel::result::Error::NotFound))
+    ));
+    assert_eq!(as_of(valid_from).title, "draft");
+    assert_eq!(as_of(inserted).title, "draft");
+    assert_eq!(as_of(history[0].valid_to).title, "review");
+    assert_eq!(as_of(updated).title, "review");
+    assert_eq!(as_of(current).title, "final");
+
+    doc.remove(&db).unwrap();
+    let removed = tick();
+    let history = Document::history(&db, &doc.id).unwrap();
+    assert_eq!(history.len(), 3);
+    assert_eq!(history[2].model.title, "final");
+    assert_eq!(as_of(current).title, "final");
+    assert!(matches!(
+        Document::query_by_id_as_of(&db, &doc.id, removed),
+        Err(ConnectionError::Query(diesel::result::Error::NotFound))
+    ));
+
+    // removing a missing model does not archive anything
+    doc.remove(&db).unwrap();
+    assert_eq!(Document::history(&db, &doc.id).unwrap().len(), 3);
+    assert!(Document::history(&db, &42).unwrap().is_empty());
+}
+
+#[test]
+fn composite_primary_key() {
+    let db = setup();
+    let mut first = Revision {
+        document_id: 1,
+        revision: 1,
+        content: "first".into(),
+    };
+    first.insert(&db).unwrap();
+    Revision {
+        document_id: 1,
+        revision: 2,
+        content: "other".into(),
+    }
+    .insert(&db)
+    .unwrap();
+    let inserted = tick();
+    first.content = "changed".into();
+    first.update(&db).unwrap();
+
+    let history = Revision::history(&db, &(1, 1)).unwrap();
+    assert_eq!(
+        history,
+        vec![Version {
+            model: Revision {
+                document_id: 1,
+                revision: 1,
+                content: "first".into(),
+            },
+            valid_from: history[0].valid_from,
+            valid_to: history[0].valid_to,
+        }]
+    );
+    assert!(history[0].valid_from.unwrap() < inserted);
+    assert!(Revision::history(&db, &(1, 2)).unwrap().is_empty());
+    let revision =
+        Revision::query_by_primary_key_as_of(&db, &(1, 1), inserted).unwrap();
+    assert_eq!(revision.content, "first");
+}
+
+#[test]
+fn quoted_identifiers() {
+    let db = setup();
+    let mut order = Order {
+        id: 0,
+        group_name: "first".into(),
+    };
+    order.insert(&db).unwrap();
+    let inserted = tick();
+    order.group_name = "second".into();
+    order.update(&db).unwrap();
+    let updated = tick();
+
+    let history = Order::history(&db, &order.id).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].model.group_name, "first");
+    let as_of = |t| Order::query_by_id_as_of(&db, &order.id, t).unwrap();
+    assert_eq!(as_of(inserted).group_name, "first");
+    assert_eq!(as_of(updated).group_name, "second");
+}
+
+#[test]
+fn same_transaction() {
+    // the history table is missing
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    db.custom(|c| diesel::sql_query(CREATE_TABLES[0]).execute(c))
+        .unwrap();
+    let rows = || {
+        db.custom(|c| schema::documents::table.load::<Document>(c))
+            .unwrap()
+    };
+
+    let mut doc = document("draft");
+    assert!(doc.insert(&db).is_err());
+    assert!(rows().is_empty());
+
+    db.custom(|c| {
+        diesel::sql_query(
+            "INSERT INTO documents (id, title) VALUES (1, 'draft')",
+        )
+        .execute(c)
+    })
+    .unwrap();
+    doc.id = 1;
+    doc.title = "review".into();
+    assert!(doc.update(&db).is_err());
+    assert!(doc.remove(&db).is_err());
+    let rows = rows();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].title, "draft");
+}
+
+#[test]
+fn any_connection() {
+    let db: DatabaseConnection<AnyConnection> =
+        DatabaseConnection::connect(":memory:").unwrap();
+    for table in CREATE_TABLES {
+        db.custom(|c| match c {
+            AnyConnection::Sqlite(c) => diesel::sql_query(*table).execute(c),
+            #[allow(unreachable_patterns)]
+            _ => unreachable!(),
+        })
+        .unwrap();
+    }
+
+    let mut doc = document("draft");
+    doc.insert(&db).unwrap();
+    let inserted = tick();
+    doc.title = "review".into();
+    doc.update(&db).unwrap();
+    doc.remove(&db).unwrap();
+    let history = Document::history(&db, &doc.id).unwrap();
+    assert_eq!(history.len(), 2);
+    let doc = Document::query_by_id_as_of(&db, &doc.id, inserted).unwrap();
+    assert_eq!(doc.title, "draft");
+}
+
+#[cfg(feature = "barrel-sqlite")]
+#[test]
+fn migration() {
+    use crate::barrel::DatabaseSqlMigrationExecutor;
+
+    let db: Database = DatabaseConnection::connect(":memory:").unwrap();
+    Document::execute_migration_up(&db).unwrap();
+    let mut doc = document("draft");
+    doc.insert(&db).unwrap();
+    doc.title = "review".into();
+    doc.update(&db).unwrap();
+    assert_eq!(Document::history(&db, &doc.id).unwrap().len(), 1);
+
+    Document::execute_migration_down(&db).unwrap();
+    assert!(Document::history(&db, &doc.id).is_err());
+}
@@ -0,0 +1,110 @@
+//! A minimal subscriber collecting the spans created by naphtha.
+
+use {
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    },
+    tracing_core::span::Current,
+    tracing_dep::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        subscriber::with_default,
+        Event,
+        Metadata,
+        Subscriber,
+    },
+};
+
+/// A span with its name and the recorded fields formatted with `Debug`.
+#[derive(Debug, Default, Clone)]
+pub struct CapturedSpan {
+    pub name: String,
+    pub fields: HashMap<String, String>,
+}
+
+impl CapturedSpan {
+    /// Returns `true` if the given field has been recorded.
+    pub fn has(&self, field: &str) -> bool {
+        self.fields.contains_key(field)
+    }
+
+    /// Returns the recorded value of the given field.
+    pub fn field(&self, field: &str) -> &str {
+        self.fields
+            .get(field)
+            .unwrap_or_else(|| panic!("{} has not been recorded", field))
+    }
+}
+
+impl Visit for CapturedSpan {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.fields
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields
+            .insert(field.name().to_string(), value.to_string());
+    }
+}
+
+#[derive(Default, Clone)]
+struct Collector {
+    spans: Arc<Mutex<Vec<CapturedSpan>>>,
+    metadata: Arc<Mutex<Vec<&'static Metadata<'static>>>>,
+    stack: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Subscriber for Collector {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == "naphtha"
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut span = CapturedSpan {
+            name: attributes.metadata().name().to_string(),
+            ..CapturedSpan::default()
+        };
+        attributes.record(&mut span);
+        self.metadata.lock().unwrap().push(attributes.metadata());
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span);
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut spans[span.into_u64() as usize - 1]);
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        self.stack.lock().unwrap().push(span.into_u64());
+    }
+
+    fn exit(&self, _span: &Id) {
+        self.stack.lock().unwrap().pop();
+    }
+
+    fn current_span(&self) -> Current {
+        match self.stack.lock().unwrap().last() {
+            Some(id) => {
+                let metadata = self.metadata.lock().unwrap()[*id as usize - 1];
+                Current::new(Id::from_u64(*id), metadata)
+            }
+            None => Current::none(),
+        }
+    }
+}
+
+/// Runs the given function and returns the spans created by it.
+pub fn capture<F: FnOnce()>(f: F) -> Vec<CapturedSpan> {
+    let collector = Collector::default();
+    with_default(collector.clone(), f);
+    let spans = collector.spans.lock().unwrap().clone();
+    spans
+}
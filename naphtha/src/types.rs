@@ -0,0 +1,206 @@
+//! Value types that can be used as members of your models on SQLite and
+//! MySQL.
+//!
+//! PostgreSQL supports `uuid::Uuid` natively, use it with
+//! `diesel::sql_types::Uuid` there. As the diesel traits of a type are
+//! implemented for all enabled backends, a model with a UUID member can not
+//! be used by a build enabling PostgreSQL together with SQLite or MySQL.
+
+use {
+    crate::sql_types,
+    diesel::{
+        backend::Backend,
+        deserialize::{self, FromSql, FromSqlRow, Queryable},
+        expression::{bound::Bound, AsExpression},
+        row::Row,
+        serialize::{self, Output, ToSql},
+        sql_types::Nullable,
+    },
+    std::{fmt, io::Write, ops::Deref, str::FromStr},
+    uuid_dep::Uuid as UuidValue,
+};
+
+/// Wrapper around [uuid::Uuid](uuid_dep::Uuid) that can be stored on SQLite
+/// and MySQL, see [sql_types::Uuid]. Useful as primary key that is generated
+/// by your application, see the `key_strategy` parameter of the
+/// [model](crate::model) attribute.
+///
+/// A wrapper is required because of the orphan rule: neither the diesel
+/// traits nor `uuid::Uuid` are defined by this crate, so they cannot be
+/// implemented for `uuid::Uuid` directly. Diesel itself only implements them
+/// for PostgreSQL. It dereferences to the wrapped value and converts from and
+/// into it by [From].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid(pub UuidValue);
+
+impl Uuid {
+    /// Creates a new random (version 4) UUID.
+    pub fn new_v4() -> Self {
+        Self(UuidValue::new_v4())
+    }
+
+    /// The nil UUID, all bits set to zero.
+    pub fn nil() -> Self {
+        Self(UuidValue::nil())
+    }
+}
+
+impl Deref for Uuid {
+    type Target = UuidValue;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<UuidValue> for Uuid {
+    fn from(uuid: UuidValue) -> Self {
+        Self(uuid)
+    }
+}
+
+impl From<Uuid> for UuidValue {
+    fn from(uuid: Uuid) -> Self {
+        uuid.0
+    }
+}
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for Uuid {
+    type Err = uuid_dep::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        UuidValue::parse_str(s).map(Self)
+    }
+}
+
+// The following implementations are equal to the ones derived by
+// `AsExpression` and `FromSqlRow` of diesel 1.4, which places them in a
+// function body and therefore triggers the `non_local_definitions` lint.
+
+/// Implements [AsExpression] of [Uuid] and its references for the given SQL
+/// type.
+macro_rules! impl_as_expression {
+    ($sql_type:ty) => {
+        impl AsExpression<$sql_type> for Uuid {
+            type Expression = Bound<$sql_type, Self>;
+
+            fn as_expression(self) -> Self::Expression {
+                Bound::new(self)
+            }
+        }
+
+        impl<'a> AsExpression<$sql_type> for &'a Uuid {
+            type Expression = Bound<$sql_type, Self>;
+
+            fn as_expression(self) -> Self::Expression {
+                Bound::new(self)
+            }
+        }
+
+        impl<'a, 'b> AsExpression<$sql_type> for &'b &'a Uuid {
+            type Expression = Bound<$sql_type, Self>;
+
+            fn as_expression(self) -> Self::Expression {
+                Bound::new(self)
+            }
+        }
+    };
+}
+
+impl_as_expression!(sql_types::Uuid);
+impl_as_expression!(Nullable<sql_types::Uuid>);
+
+impl<DB> ToSql<Nullable<sql_types::Uuid>, DB> for Uuid
+where
+    DB: Backend,
+    Self: ToSql<sql_types::Uuid, DB>,
+{
+    fn to_sql<W: Write>(&self, out: &mut Output<W, DB>) -> serialize::Result {
+        ToSql::<sql_types::Uuid, DB>::to_sql(self, out)
+    }
+}
+
+impl<ST, DB> FromSqlRow<ST, DB> for Uuid
+where
+    DB: Backend,
+    Self: FromSql<ST, DB>,
+{
+    fn build_from_row<R: Row<DB>>(row: &mut R) -> deserialize::Result<Self> {
+        FromSql::<ST, DB>::from_sql(row.take())
+    }
+}
+
+impl<ST, DB> Queryable<ST, DB> for Uuid
+where
+    DB: Backend,
+    Self: FromSql<ST, DB>,
+{
+    type Row = Self;
+
+    fn build(row: Self::Row) -> Self {
+        row
+    }
+}
+
+/// Serializes the hyphenated string representation.
+#[cfg(any(feature = "audit", feature = "serde"))]
+impl crate::serde::Serialize for Uuid {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: crate::serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(any(feature = "audit", feature = "serde"))]
+impl<'de> crate::serde::Deserialize<'de> for Uuid {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: crate::serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(crate::serde::de::Error::custom)
+    }
+}
+
+/// Implements the conversion of [Uuid] using its text representation.
+macro_rules! impl_uuid_as_text {
+    ($backend:ty) => {
+        impl ToSql<sql_types::Uuid, $backend> for Uuid {
+            fn to_sql<W: Write>(
+                &self,
+                out: &mut Output<W, $backend>,
+            ) -> serialize::Result {
+                let value = self.0.to_hyphenated().to_string();
+                ToSql::<diesel::sql_types::Text, $backend>::to_sql(&value, out)
+            }
+        }
+
+        impl FromSql<sql_types::Uuid, $backend> for Uuid {
+            fn from_sql(
+                bytes: Option<
+                    &<$backend as diesel::backend::Backend>::RawValue,
+                >,
+            ) -> deserialize::Result<Self> {
+                let value = <String as FromSql<
+                    diesel::sql_types::Text,
+                    $backend,
+                >>::from_sql(bytes)?;
+                value.parse().map_err(|e: uuid_dep::Error| e.into())
+            }
+        }
+    };
+}
+
+#[cfg(feature = "sqlite")]
+impl_uuid_as_text!(diesel::sqlite::Sqlite);
+
+#[cfg(feature = "mysql")]
+impl_uuid_as_text!(diesel::mysql::Mysql);